@@ -1,75 +1,594 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+
 use crate::util;
+use crate::util::rng::Rng;
 
 pub fn run_part_1(args: &[String]) {
-    util::run_part_n("1", args, calculate_frequency);
+    util::run_part_n_with_options("1", args, calculate_frequency);
 }
 
 pub fn run_part_2(args: &[String]) {
-    util::run_part_n("1", args, calculate_repeat_frequency);
+    util::run_part_n_with_options("1", args, calculate_repeat_frequency);
+}
+
+pub fn run_stream(args: &[String]) {
+    util::run_with_options("1", args, stream_frequencies);
+}
+
+pub fn run_stats(args: &[String]) {
+    util::run_part_n_with_options("1", args, report_frequency_statistics);
+}
+
+fn calculate_repeat_frequency(input_filename: &String, options: &util::Options) -> util::AppResult {
+    let input = read_file_input(input_filename, options)?;
+    let result = calculate_repeat(&input)?
+        .ok_or_else(|| util::AppError::AppError(String::from("No frequency is ever reached twice")))?;
+
+    println!("first repeat frequency: {}", result.frequency);
+    println!("repeated by change {} on pass {} after {} steps", result.index + 1, result.pass + 1, result.steps);
+    println!("first reached after {} steps", result.first_reached);
+
+    if let Some(trace_filename) = options.value("trace") {
+        let limit = options.parse_value::<u64>("trace-limit")?.unwrap_or(DEFAULT_TRACE_LIMIT);
+        let written = result.steps.min(limit as i128);
+        write_trace(&input, written, trace_filename)?;
+
+        if written < result.steps {
+            println!("Frequency trace of the first {} of {} steps written to {}, use --trace-limit to write more", written, result.steps, trace_filename);
+        } else {
+            println!("Frequency trace written to {}", trace_filename);
+        }
+    }
+
+    Ok(())
+}
+
+/// Steps written to a trace unless `--trace-limit` says otherwise; a repeat can
+/// take far more steps than are worth writing out.
+const DEFAULT_TRACE_LIMIT: u64 = 1_000_000;
+
+/// Writes the frequency after each step, up to and including `steps`, as CSV.
+fn write_trace(input: &[i64], steps: i128, filename: &str) -> util::AppResult {
+    let mut writer = BufWriter::new(File::create(filename)?);
+    let mut current: i64 = 0;
+
+    writeln!(writer, "step,frequency")?;
+    writeln!(writer, "0,0")?;
+
+    for (step, num) in (1..=steps).zip(input.iter().cycle()) {
+        current = current.checked_add(*num).ok_or_else(|| overflow_error(step))?;
+        writeln!(writer, "{},{}", step, current)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
 }
 
-fn calculate_repeat_frequency(input_filename: &String) -> util::AppResult {
-    let input = read_file_input(input_filename)?;
-    let result = calculate_repeat(&input);
+/// Applies frequency changes as they arrive on stdin, reporting after every line.
+/// A repeat is only looked for in the changes seen so far; `--replay` also runs
+/// part 2 over the whole stream once it ends.
+fn stream_frequencies(options: &util::Options) -> util::AppResult {
+    let mode = if options.flag("lenient") { ParseMode::Lenient } else { ParseMode::Strict };
+    let mut stream = FrequencyStream::new();
+    let stdin = io::stdin();
+
+    for (line_number, line) in stdin.lock().lines().enumerate() {
+        for change in parse_line(&line?, line_number + 1, mode)? {
+            stream.push(change)?;
+        }
 
-    println!("first repeat frequency: {}", result);
+        match stream.repeat {
+            Some((frequency, step)) => println!("frequency {}, first repeat {} at step {}", stream.current, frequency, step),
+            None => println!("frequency {}, no repeat yet", stream.current),
+        }
+    }
+
+    if options.flag("replay") {
+        match calculate_repeat(&stream.changes)? {
+            Some(repeat) => println!("replaying the stream: first repeat {} after {} steps", repeat.frequency, repeat.steps),
+            None => println!("replaying the stream: no frequency is ever reached twice"),
+        }
+    }
 
     Ok(())
 }
 
-fn calculate_frequency(input_filename: &String) -> util::AppResult {
-    let input = read_file_input(input_filename)?;
-    let result = calculate(&input);
+/// Running state for frequency changes that arrive one at a time.
+struct FrequencyStream {
+    current: i64,
+    changes: Vec<i64>,
+    seen: HashSet<i64>,
+    /// The first frequency reached twice and the step it happened on.
+    repeat: Option<(i64, usize)>,
+}
+
+impl FrequencyStream {
+    fn new() -> FrequencyStream {
+        let mut seen = HashSet::new();
+        seen.insert(0);
+
+        FrequencyStream {
+            current: 0,
+            changes: Vec::new(),
+            seen,
+            repeat: None,
+        }
+    }
+
+    fn push(&mut self, change: i64) -> util::AppResult {
+        self.changes.push(change);
+        self.current = self.current.checked_add(change).ok_or_else(|| overflow_error(self.changes.len() as i128))?;
+
+        if !self.seen.insert(self.current) && self.repeat.is_none() {
+            self.repeat = Some((self.current, self.changes.len()));
+        }
+
+        Ok(())
+    }
+}
+
+fn report_frequency_statistics(input_filename: &String, options: &util::Options) -> util::AppResult {
+    let input = read_file_input(input_filename, options)?;
+    let passes = options.parse_value::<usize>("passes")?.unwrap_or(10);
+    let shown = options.parse_value::<usize>("repeats")?.unwrap_or(10);
+    let report = frequency_report(&input, passes)?;
+
+    println!("over {} passes:", passes);
+    println!("frequency range: {} to {}", report.min, report.max);
+    println!("distinct frequencies: {}", report.distinct);
+    println!("repeated frequencies: {}", report.repeats.len());
+
+    for (frequency, count) in report.repeats.iter().take(shown) {
+        println!("  {} reached {} times", frequency, count);
+    }
+
+    Ok(())
+}
+
+fn calculate_frequency(input_filename: &String, options: &util::Options) -> util::AppResult {
+    let input = read_file_input(input_filename, options)?;
+    let result = calculate(&input)?;
 
     println!("resulting frequency: {}", result);
 
     Ok(())
 }
 
-fn calculate(input: &Vec<i32>) -> i32 {
-    let mut acc = 0;
+/// `size` frequency changes, one per line.
+pub fn generate_input(size: usize, rng: &mut Rng) -> String {
+    (0..size)
+        .map(|_| format!("{:+}", rng.range(-100_000, 100_000)))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn calculate(input: &[i64]) -> util::AppResult<i64> {
+    let mut acc: i64 = 0;
 
-    for num in input.iter() {
-        acc += num;
+    for (step, num) in input.iter().enumerate() {
+        acc = acc.checked_add(*num).ok_or_else(|| overflow_error(step as i128 + 1))?;
     }
 
-    acc
+    Ok(acc)
+}
+
+fn overflow_error(step: i128) -> util::AppError {
+    util::AppError::AppError(format!("Frequency overflowed at step {}", step))
 }
 
-fn calculate_repeat(input: &Vec<i32>) -> i32 {
-    let mut current = 0;
-    let mut cache = std::collections::HashSet::new();
+/// Statistics over every frequency reached in a number of passes.
+#[derive(Debug, PartialEq, Eq)]
+struct FrequencyReport {
+    min: i64,
+    max: i64,
+    distinct: usize,
+    /// Frequencies reached more than once, in the order they first repeated,
+    /// with the number of times each was reached.
+    repeats: Vec<(i64, usize)>,
+}
+
+fn frequency_report(input: &[i64], passes: usize) -> util::AppResult<FrequencyReport> {
+    let mut current: i64 = 0;
+    let mut counts = HashMap::new();
+    let mut repeat_order = Vec::new();
+    let (mut min, mut max) = (0, 0);
+
+    counts.insert(current, 1);
+
+    let steps = input.len().checked_mul(passes)
+        .ok_or_else(|| util::AppError::AppError(format!("Too many passes: {}", passes)))?;
+
+    for (step, num) in input.iter().cycle().take(steps).enumerate() {
+        current = current.checked_add(*num).ok_or_else(|| overflow_error(step as i128 + 1))?;
+        min = std::cmp::min(min, current);
+        max = std::cmp::max(max, current);
+
+        let count = counts.entry(current).or_insert(0);
+        *count += 1;
+        if *count == 2 {
+            repeat_order.push(current);
+        }
+    }
+
+    Ok(FrequencyReport {
+        min,
+        max,
+        distinct: counts.len(),
+        repeats: repeat_order.into_iter().map(|f| (f, counts[&f])).collect(),
+    })
+}
 
-    cache.insert(current);
+/// Finds the first frequency reached twice without simulating pass after pass.
+///
+/// After `t` changes the frequency is `sums[t % n] + (t / n) * drift`, where `sums`
+/// holds the frequencies seen during the first pass (starting at 0) and `drift` is
+/// the change over a whole pass. A later pass can only land on `sums[i]` from a
+/// `sums[j]` with the same remainder modulo `drift`, and the closest one behind it
+/// gets there first. Returns `None` if no frequency ever repeats, and fails if any
+/// frequency on the way to the repeat doesn't fit in an `i64`.
+fn calculate_repeat(input: &[i64]) -> util::AppResult<Option<Repeat>> {
+    let mut sums = Vec::with_capacity(input.len() + 1);
+    let mut first_seen = HashMap::new();
+    let mut current: i64 = 0;
+    sums.push(current);
+    first_seen.insert(current, 0);
 
-    loop {
-        for num in input.iter() {
-            current += num;
-            if cache.contains(&current) {
-                return current;
+    for (index, num) in input.iter().enumerate() {
+        let step = index + 1;
+        current = current.checked_add(*num).ok_or_else(|| overflow_error(step as i128))?;
+        sums.push(current);
+
+        // A repeat within the first pass beats anything a later pass can produce,
+        // and an overflow further along the pass never gets reached.
+        if step < input.len() {
+            if let Some(&first) = first_seen.get(&current) {
+                return Ok(Some(Repeat::new(current, step as i128, first, input.len())));
             }
 
-            cache.insert(current);
+            first_seen.insert(current, step);
+        }
+    }
+
+    // Pass arithmetic is done in i128 so that a drift near the limits can't overflow.
+    let drift = current as i128;
+    let n = input.len() as i128;
+
+    if input.is_empty() {
+        return Ok(None);
+    } else if drift == 0 {
+        // Every pass retraces the first, so the second pass starts back at 0.
+        return Ok(Some(Repeat::new(0, n, 0, input.len())));
+    }
+
+    let mut groups: HashMap<i128, Vec<(i64, usize)>> = HashMap::new();
+    for (&sum, &index) in first_seen.iter() {
+        groups.entry((sum as i128).rem_euclid(drift.abs())).or_default().push((sum, index));
+    }
+
+    let mut best: Option<Repeat> = None;
+
+    for group in groups.values_mut() {
+        group.sort();
+        if drift < 0 {
+            group.reverse();
         }
+
+        for pair in group.windows(2) {
+            let ((from, from_index), (to, to_index)) = (pair[0], pair[1]);
+            let passes = (to as i128 - from as i128) / drift;
+            let steps = passes * n + from_index as i128;
+
+            if best.as_ref().is_none_or(|b| steps < b.steps) {
+                best = Some(Repeat::new(to, steps, to_index, input.len()));
+            }
+        }
+    }
+
+    if let Some(repeat) = &best {
+        check_frequencies_fit(&sums, repeat)?;
     }
+
+    Ok(best)
 }
 
-fn read_file_input(filename: &String) -> Result<Vec<i32>, util::AppError> {
+/// Later passes shift every frequency by the drift, so the extremes reached before
+/// `repeat` can lie outside the first pass's range. Fails if they don't fit in an `i64`.
+fn check_frequencies_fit(sums: &[i64], repeat: &Repeat) -> util::AppResult {
+    if repeat.pass == 0 {
+        return Ok(());
+    }
+
+    let drift = sums[sums.len() - 1] as i128;
+    let after_change = &sums[1..];
+
+    // Only the side the drift is heading towards can grow past the first pass.
+    let extreme = |values: &[i64]| if drift > 0 {
+        values.iter().max().map(|&v| v as i128)
+    } else {
+        values.iter().min().map(|&v| v as i128)
+    };
+
+    let full_passes = extreme(after_change).unwrap() + (repeat.pass - 1) * drift;
+    let last_pass = extreme(&after_change[..=repeat.index]).unwrap() + repeat.pass * drift;
+
+    for value in [full_passes, last_pass].iter() {
+        if *value > i64::MAX as i128 || *value < i64::MIN as i128 {
+            return Err(util::AppError::AppError(String::from("Frequency overflows before any frequency repeats")));
+        }
+    }
+
+    Ok(())
+}
+
+/// Where the first repeated frequency occurs. Steps count the changes applied,
+/// so step 0 is the starting frequency.
+#[derive(Debug, PartialEq, Eq)]
+struct Repeat {
+    frequency: i64,
+    /// Steps taken when the frequency was reached for the second time.
+    steps: i128,
+    /// Pass over the input (from 0) and index of the change that reached it again.
+    pass: i128,
+    index: usize,
+    /// Steps taken when the frequency was first reached, always within the first pass.
+    first_reached: usize,
+}
+
+impl Repeat {
+    fn new(frequency: i64, steps: i128, first_reached: usize, input_len: usize) -> Repeat {
+        Repeat {
+            frequency,
+            steps,
+            pass: (steps - 1) / input_len as i128,
+            index: ((steps - 1) % input_len as i128) as usize,
+            first_reached,
+        }
+    }
+}
+
+fn read_file_input(filename: &String, options: &util::Options) -> Result<Vec<i64>, util::AppError> {
     let input = util::read_file_input(filename)?;
-    let vec = parse_input(&input);
+    let mode = if options.flag("lenient") { ParseMode::Lenient } else { ParseMode::Strict };
+
+    parse_input(&input, mode)
+}
 
-    Ok(vec)
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum ParseMode {
+    /// Any value that isn't a number fails the whole parse.
+    Strict,
+    /// Invalid values are reported and skipped.
+    Lenient,
 }
 
-fn parse_input(input: &String) -> Vec<i32> {
+/// Reads frequency changes one per line or comma separated (`+1, -2, +3`).
+/// Blank lines and empty entries are ignored.
+fn parse_input(input: &str, mode: ParseMode) -> Result<Vec<i64>, util::AppError> {
     let mut result = Vec::new();
 
-    for line in input.lines() {
-        match line.parse::<i32>() {
+    for (line_number, line) in input.lines().enumerate() {
+        result.extend(parse_line(line, line_number + 1, mode)?);
+    }
+
+    Ok(result)
+}
+
+fn parse_line(line: &str, line_number: usize, mode: ParseMode) -> Result<Vec<i64>, util::AppError> {
+    let mut result = Vec::new();
+
+    for value in line.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        match value.parse::<i64>() {
             Ok(num) => result.push(num),
-            Err(e) => println!("Invalid value in input: {}", e)
+            Err(e) => {
+                let message = format!("Invalid value '{}' on line {}: {}", value, line_number, e);
+                match mode {
+                    ParseMode::Strict => return Err(util::AppError::AppError(message)),
+                    ParseMode::Lenient => println!("{}, skipping", message),
+                }
+            },
         };
     }
 
-    result
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::prop;
+
+    #[test]
+    fn test_parse_input_formats() {
+        assert_eq!(vec![1, -2, 3, 1], parse_input("+1, -2, +3, +1", ParseMode::Strict).unwrap());
+        assert_eq!(vec![1, -2, 3], parse_input("+1\n\n-2\n3\n", ParseMode::Strict).unwrap());
+        assert_eq!(vec![7, 7, -2], parse_input("+7,+7\n  -2 ,\n", ParseMode::Strict).unwrap());
+        assert!(parse_input("", ParseMode::Strict).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_input_strict_reports_line() {
+        let error = parse_input("+1\n-2\n\n+x3\n", ParseMode::Strict).unwrap_err();
+
+        assert!(error.to_string().contains("line 4"));
+        assert!(error.to_string().contains("+x3"));
+    }
+
+    #[test]
+    fn test_parse_input_lenient_skips() {
+        assert_eq!(vec![1, 3], parse_input("+1\nfoo\n+3", ParseMode::Lenient).unwrap());
+    }
+
+    #[test]
+    fn test_calculate_repeat() {
+        assert_eq!(Some(0), repeat_frequency(&[1, -1]));
+        assert_eq!(Some(10), repeat_frequency(&[3, 3, 4, -2, -4]));
+        assert_eq!(Some(5), repeat_frequency(&[-6, 3, 8, 5, -6]));
+        assert_eq!(Some(14), repeat_frequency(&[7, 7, -2, -7, -4]));
+        assert_eq!(Some(2), repeat_frequency(&[1, -2, 3, 1]));
+    }
+
+    #[test]
+    fn test_calculate_repeat_never_repeats() {
+        assert_eq!(None, repeat_frequency(&[1, 1]));
+        assert_eq!(None, repeat_frequency(&[-5]));
+        assert_eq!(None, repeat_frequency(&[]));
+    }
+
+    #[test]
+    fn test_calculate_repeat_zero_drift() {
+        assert_eq!(Some(0), repeat_frequency(&[0]));
+        assert_eq!(Some(0), repeat_frequency(&[4, -1, -3]));
+        assert_eq!(Some(3), repeat_frequency(&[3, 2, -2, -3]));
+    }
+
+    #[test]
+    fn test_calculate_repeat_explains_position() {
+        // 0, 1, -1, 2, 3, 4, 2: reached 2 first after 3 steps, again on the 3rd change of pass 2.
+        let repeat = calculate_repeat(&[1, -2, 3, 1]).unwrap().unwrap();
+        assert_eq!(Repeat { frequency: 2, steps: 6, pass: 1, index: 1, first_reached: 3 }, repeat);
+
+        // 0, 3, 5, 3: a repeat within the first pass.
+        let repeat = calculate_repeat(&[3, 2, -2, -3]).unwrap().unwrap();
+        assert_eq!(Repeat { frequency: 3, steps: 3, pass: 0, index: 2, first_reached: 1 }, repeat);
+
+        // 0, 1, 0: back at the start.
+        let repeat = calculate_repeat(&[1, -1]).unwrap().unwrap();
+        assert_eq!(Repeat { frequency: 0, steps: 2, pass: 0, index: 1, first_reached: 0 }, repeat);
+    }
+
+    #[test]
+    fn test_calculate_near_limits() {
+        assert_eq!(i64::MAX, calculate(&[i64::MAX - 1, 1]).unwrap());
+        assert_eq!(i64::MIN, calculate(&[-1, i64::MIN + 1]).unwrap());
+        assert_eq!(-1, calculate(&[i64::MAX, i64::MIN + 1, -1]).unwrap());
+        assert!(calculate(&[i64::MAX, 1]).is_err());
+        assert!(calculate(&[i64::MIN, -1]).is_err());
+    }
+
+    #[test]
+    fn test_parse_input_limits() {
+        assert_eq!(vec![i64::MAX, i64::MIN], parse_input("+9223372036854775807\n-9223372036854775808", ParseMode::Strict).unwrap());
+        assert!(parse_input("+9223372036854775808", ParseMode::Strict).is_err());
+    }
+
+    #[test]
+    fn test_calculate_repeat_near_limits() {
+        // Creeps up by 1 per pass until the second change lands back on `half`.
+        let half = i64::MAX / 2;
+        let repeat = calculate_repeat(&[half, 1 - half]).unwrap().unwrap();
+        assert_eq!(half, repeat.frequency);
+        assert_eq!(2 * half as i128, repeat.steps);
+
+        // The same shape starting from the maximum would need i64::MAX + 1 first.
+        assert!(calculate_repeat(&[i64::MAX, 1 - i64::MAX]).is_err());
+        assert!(calculate_repeat(&[i64::MIN + 1, i64::MAX - 1]).is_err());
+        assert!(calculate_repeat(&[i64::MAX, 1]).is_err());
+
+        // 0 comes round again at step 2, before the overflow at step 4.
+        let repeat = calculate_repeat(&[1, -1, i64::MAX, 1]).unwrap().unwrap();
+        assert_eq!(0, repeat.frequency);
+        assert_eq!(2, repeat.steps);
+    }
+
+    #[test]
+    fn test_frequency_report() {
+        // 0, 1, -1, 2, 3, 4, 2, 5, 6, 7, 5, 8, 9, 10, 8, 11, 12
+        let report = frequency_report(&[1, -2, 3, 1], 4).unwrap();
+
+        assert_eq!(FrequencyReport { min: -1, max: 12, distinct: 14, repeats: vec![(2, 2), (5, 2), (8, 2)] }, report);
+    }
+
+    #[test]
+    fn test_frequency_report_counts() {
+        let report = frequency_report(&[1, -1], 3).unwrap();
+
+        assert_eq!(FrequencyReport { min: 0, max: 1, distinct: 2, repeats: vec![(0, 4), (1, 3)] }, report);
+        assert_eq!(Some(&(0, 4)), report.repeats.first());
+        assert!(frequency_report(&[i64::MAX, -i64::MAX], 2).is_ok());
+        assert!(frequency_report(&[i64::MAX, 1], 1).is_err());
+        assert!(frequency_report(&[1, -1], usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_frequency_stream() {
+        let mut stream = FrequencyStream::new();
+
+        for change in [3, 3, 4, -2].iter() {
+            stream.push(*change).unwrap();
+        }
+        assert_eq!(8, stream.current);
+        assert_eq!(None, stream.repeat);
+
+        stream.push(-4).unwrap();
+        stream.push(-1).unwrap();
+        stream.push(1).unwrap();
+        assert_eq!(Some((3, 6)), stream.repeat);
+        assert_eq!(4, stream.current);
+
+        // Replaying the whole stream finds the repeat part 2 would.
+        assert_eq!(Some(3), repeat_frequency(&stream.changes));
+    }
+
+    #[test]
+    fn test_frequency_stream_overflow() {
+        let mut stream = FrequencyStream::new();
+        stream.push(i64::MAX).unwrap();
+
+        assert!(stream.push(1).is_err());
+    }
+
+    #[test]
+    fn test_write_trace() {
+        let filename = std::env::temp_dir().join("day_01_test_trace.csv");
+        let filename = filename.to_str().unwrap();
+
+        write_trace(&[1, -2, 3, 1], 6, filename).unwrap();
+        let trace = util::read_file_input(&String::from(filename)).unwrap();
+        std::fs::remove_file(filename).unwrap();
+
+        assert_eq!("step,frequency\n0,0\n1,1\n2,-1\n3,2\n4,3\n5,4\n6,2\n", trace);
+    }
+
+    #[test]
+    fn test_calculate_repeat_matches_simulation() {
+        let random_input = |rng: &mut Rng| (0..rng.range(1, 8)).map(|_| rng.range(-10, 10)).collect::<Vec<i64>>();
+
+        prop::check("calculate_repeat matches simulation", random_input, |input| {
+            repeat_frequency(input) == simulate_repeat(input, 1000)
+        });
+    }
+
+    fn repeat_frequency(input: &[i64]) -> Option<i64> {
+        calculate_repeat(input).unwrap().map(|r| r.frequency)
+    }
+
+    /// The straightforward walk, giving up after `max_passes`.
+    fn simulate_repeat(input: &[i64], max_passes: usize) -> Option<i64> {
+        let mut current = 0;
+        let mut seen = HashSet::new();
+        seen.insert(current);
+
+        for _ in 0..max_passes {
+            for num in input.iter() {
+                current += num;
+                if !seen.insert(current) {
+                    return Some(current);
+                }
+            }
+        }
+
+        None
+    }
+
+    #[test]
+    fn test_generated_input_parses() {
+        let input = generate_input(500, &mut Rng::new(1));
+        let parsed = parse_input(&input, ParseMode::Strict).unwrap();
+
+        assert_eq!(500, parsed.len());
+        assert_eq!(input, generate_input(500, &mut Rng::new(1)));
+    }
 }
@@ -1,27 +1,79 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
 
 use crate::util;
+use crate::util::bk_tree::BkTree;
+use crate::util::pairs::unordered_pairs;
+use crate::util::rng::Rng;
+use crate::util::union_find::UnionFind;
 
 pub fn run_part_1(args: &[String]) {
-    util::run_part_n("2", args, calculate_checksum);
+    util::run_part_n_with_options("2", args, calculate_checksum);
 }
 
 pub fn run_part_2(args: &[String]) {
-    util::run_part_n("2", args, find_similar_characters);
+    util::run_part_n_with_options("2", args, find_similar_characters);
 }
 
-fn calculate_checksum(filename: &String) -> util::AppResult {
+pub fn run_query(args: &[String]) {
+    util::run_part_n_with_options("2", args, query_ids);
+}
+
+fn calculate_checksum(filename: &String, options: &util::Options) -> util::AppResult {
     let input = util::read_file_input(filename)?;
-    let result = calculate_checksums(&input);
+    let multiplicities = match options.value("multiplicities") {
+        Some(list) => parse_multiplicities(list)?,
+        None => vec![2, 3],
+    };
+
+    let report = checksum_report(&input, &multiplicities, Letters::from_options(options));
+
+    for (n, ids) in report.breakdown.iter() {
+        println!("{} IDs have a letter appearing exactly {} times", ids.len(), n);
+
+        if options.flag("breakdown") {
+            for id in ids.iter() {
+                println!("  {}", id);
+            }
+        }
+    }
 
-    println!("The checksum is {}", result);
+    println!("The checksum is {}", report.product);
 
     Ok(())
 }
 
-fn find_similar_characters(filename: &String) -> util::AppResult {
+/// Reads a comma separated list of letter counts, e.g. `2,3,4`.
+fn parse_multiplicities(list: &str) -> util::AppResult<Vec<u32>> {
+    let mut result = Vec::new();
+
+    for n in list.split(',').map(|n| n.trim()) {
+        match n.parse::<u32>() {
+            Ok(n) if n > 0 => result.push(n),
+            _ => return Err(util::AppError::AppError(format!("Invalid multiplicity: {}", n))),
+        }
+    }
+
+    Ok(result)
+}
+
+fn find_similar_characters(filename: &String, options: &util::Options) -> util::AppResult {
     let input = util::read_file_input(filename)?;
-    let result = calculate_similar(&input);
+    let letters = Letters::from_options(options);
+
+    if let Some(distance) = options.parse_value::<usize>("distance")? {
+        print_similar_pairs(&find_similar_pairs(&input, distance, letters), distance);
+        return Ok(());
+    }
+
+    if let Some(threshold) = options.parse_value::<usize>("cluster")? {
+        print_clusters(&cluster_ids(&input, threshold, letters), threshold);
+        return Ok(());
+    }
+
+    let result = calculate_similar(&input, letters);
 
     match result {
         Some(result) => println!("The common characters are {}", result),
@@ -31,147 +83,837 @@ fn find_similar_characters(filename: &String) -> util::AppResult {
     Ok(())
 }
 
-fn calculate_checksums(input: &String) -> i32 {
-    let mut twos = 0;
-    let mut threes = 0;
+fn print_similar_pairs(pairs: &[SimilarPair], distance: usize) {
+    println!("{} pairs of IDs within distance {}", pairs.len(), distance);
 
-    for word in input.lines() {
-        let counter = count_letters(word);
+    for pair in pairs.iter() {
+        let positions = pair.mismatches.iter().map(|p| p.to_string()).collect::<Vec<String>>();
+        println!("  {} {} differ at [{}], common {}", pair.first, pair.second, positions.join(", "), pair.common);
+    }
+
+    let mut groups: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for pair in pairs.iter() {
+        groups.entry(&pair.first).or_default().push(&pair.second);
+        groups.entry(&pair.second).or_default().push(&pair.first);
+    }
+
+    println!("Grouped by ID:");
+    for (id, similar) in groups.iter() {
+        println!("  {}: {}", id, similar.join(", "));
+    }
+}
+
+/// Loads the IDs from the file, then answers with the closest known ID for each ID
+/// read from stdin. `--distance` limits how far away a match may be.
+fn query_ids(filename: &String, options: &util::Options) -> util::AppResult {
+    let input = util::read_file_input(filename)?;
+    let letters = Letters::from_options(options);
+    let max_distance = options.parse_value::<usize>("distance")?.unwrap_or(usize::MAX);
+    let index = index_ids(&input, letters);
+
+    println!("Indexed {} IDs, enter an ID to find the closest", input.lines().count());
+
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        let query = String::from(line.trim());
+        if query.is_empty() {
+            continue;
+        }
 
-        if counter.values().any(|&x| x == 2) {
-            twos += 1;
+        match index.nearest(&query, max_distance) {
+            Some((id, distance)) => {
+                let edits = edit_script(&letters.split(&query), &letters.split(id));
+                let edits = edits.iter().map(|e| e.to_string()).collect::<Vec<String>>();
+
+                if edits.is_empty() {
+                    println!("{} is a known ID", id);
+                } else {
+                    println!("{} (distance {}): {}", id, distance, edits.join(", "));
+                }
+            },
+            None => println!("No ID within distance {}", max_distance),
         }
+    }
+
+    Ok(())
+}
+
+/// A BK-tree over the IDs under edit distance.
+fn index_ids(input: &str, letters: Letters) -> BkTree<String, impl Fn(&String, &String) -> usize> {
+    let mut index = BkTree::new(move |a: &String, b: &String| edit_distance(&letters.split(a), &letters.split(b)));
+
+    for id in input.lines() {
+        index.insert(String::from(id));
+    }
+
+    index
+}
+
+fn print_clusters(clusters: &[Cluster], threshold: usize) {
+    let grouped = clusters.iter().filter(|c| c.ids.len() > 1).collect::<Vec<&Cluster>>();
+    println!("{} clusters of IDs within edit distance {} ({} IDs on their own)",
+        grouped.len(), threshold, clusters.len() - grouped.len());
+
+    for cluster in grouped.iter() {
+        println!("  {} ({} IDs): {}", cluster.consensus, cluster.ids.len(), cluster.ids.join(", "));
+    }
+}
+
+/// `size` box IDs of 26 lowercase letters, two of which differ by exactly one letter.
+pub fn generate_input(size: usize, rng: &mut Rng) -> String {
+    let letters = "abcdefghijklmnopqrstuvwxyz".chars().collect::<Vec<char>>();
+    let mut ids = (0..size)
+        .map(|_| (0..26).map(|_| *rng.choose(&letters)).collect::<Vec<char>>())
+        .collect::<Vec<Vec<char>>>();
+
+    if size >= 2 {
+        let original = rng.below(size);
+        let copy = (original + 1 + rng.below(size - 1)) % size;
+        let position = rng.below(26);
+        let mut similar = ids[original].clone();
+        similar[position] = letters[(letters.iter().position(|&c| c == similar[position]).unwrap() + 1) % 26];
+        ids[copy] = similar;
+    }
 
-        if counter.values().any(|&x| x == 3) {
-            threes += 1;
+    ids.iter()
+        .map(|id| id.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// How an ID is split into the letters that are counted and compared.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Letters {
+    /// Every `char` is a letter.
+    Chars,
+    /// Combining marks, variation selectors, skin tone modifiers and anything joined
+    /// by a zero width joiner stay attached to the character before them. This
+    /// approximates extended grapheme clusters without needing the Unicode tables.
+    Graphemes,
+}
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+impl Letters {
+    /// `--graphemes` switches from `char` to grapheme letters.
+    fn from_options(options: &util::Options) -> Letters {
+        if options.flag("graphemes") {
+            Letters::Graphemes
+        } else {
+            Letters::Chars
+        }
+    }
+
+    fn split(self, id: &str) -> Vec<&str> {
+        let mut starts = Vec::new();
+        let mut previous = None;
+
+        for (i, c) in id.char_indices() {
+            let attached = match previous {
+                Some(p) => self == Letters::Graphemes && (extends_previous(c) || p == ZERO_WIDTH_JOINER),
+                None => false,
+            };
+
+            if !attached {
+                starts.push(i);
+            }
+            previous = Some(c);
         }
+
+        starts.iter()
+            .enumerate()
+            .map(|(n, &start)| &id[start..starts.get(n + 1).cloned().unwrap_or(id.len())])
+            .collect()
     }
+}
 
-    println!("{}, {}", twos, threes);
+fn extends_previous(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}' | '\u{1AB0}'..='\u{1AFF}' | '\u{1DC0}'..='\u{1DFF}' |
+        '\u{20D0}'..='\u{20FF}' | '\u{FE00}'..='\u{FE0F}' | '\u{FE20}'..='\u{FE2F}' |
+        '\u{1F3FB}'..='\u{1F3FF}' | ZERO_WIDTH_JOINER)
+}
 
-    twos * threes
+/// How many IDs have some letter appearing exactly `n` times, for each requested `n`.
+struct ChecksumReport {
+    product: u64,
+    /// Each multiplicity with the IDs that have a letter appearing that many times.
+    breakdown: Vec<(u32, Vec<String>)>,
 }
 
-fn count_letters(word: &str) -> HashMap<char, i32> {
+fn checksum_report(input: &str, multiplicities: &[u32], letters: Letters) -> ChecksumReport {
+    let mut breakdown = multiplicities.iter().map(|&n| (n, Vec::new())).collect::<Vec<(u32, Vec<String>)>>();
+
+    for word in input.lines() {
+        let counter = count_letters(word, letters);
+
+        for (n, ids) in breakdown.iter_mut() {
+            if counter.values().any(|x| x == n) {
+                ids.push(String::from(word));
+            }
+        }
+    }
+
+    ChecksumReport {
+        product: breakdown.iter().map(|(_, ids)| ids.len() as u64).product(),
+        breakdown,
+    }
+}
+
+fn count_letters(word: &str, letters: Letters) -> LetterCounts<'_> {
+    // ASCII has no combining marks, so every byte is a letter either way.
+    if word.is_ascii() {
+        let mut counts = [0; 128];
+        for byte in word.bytes() {
+            counts[byte as usize] += 1;
+        }
+        return LetterCounts::Ascii(counts);
+    }
+
     let mut result = HashMap::new();
 
-    for letter in word.chars() {
+    for letter in letters.split(word) {
         *result.entry(letter).or_insert(0) += 1;
     }
 
-    result
+    LetterCounts::Other(result)
 }
 
-fn calculate_similar(input: &String) -> Option<String> {
-    for (line1, line2) in iterate_all_lines_against_each_other(input) {
-        if let Some(matching) = compare_ids(&line1, &line2) {
-            return Some(matching)
+/// Letter counts for one ID. ASCII IDs, which is all the puzzle uses, are counted
+/// into a fixed array on the stack rather than a map. The array is what makes the
+/// variants so different in size, and boxing it would bring back the allocation.
+#[allow(clippy::large_enum_variant)]
+enum LetterCounts<'a> {
+    Ascii([u32; 128]),
+    Other(HashMap<&'a str, u32>),
+}
+
+impl LetterCounts<'_> {
+    /// Counts of the letters that appear. Only one of the two halves is ever
+    /// non-empty, which keeps the iterator type concrete.
+    fn values(&self) -> impl Iterator<Item = &u32> {
+        let (ascii, other) = match self {
+            LetterCounts::Ascii(counts) => (Some(counts.iter()), None),
+            LetterCounts::Other(counts) => (None, Some(counts.values())),
+        };
+
+        ascii.into_iter().flatten().chain(other.into_iter().flatten()).filter(|&&c| c > 0)
+    }
+}
+
+/// Finds two IDs that differ in exactly one position. Removing the position they
+/// differ in makes them equal, so for each position the IDs are bucketed by what is
+/// left once it's removed, and any bucket holding two different IDs is the answer.
+/// Buckets are keyed by the remaining letters rather than their concatenation, since
+/// graphemes could otherwise regroup.
+fn calculate_similar(input: &str, letters: Letters) -> Option<String> {
+    let ids = input.lines().map(|id| letters.split(id)).collect::<Vec<Vec<&str>>>();
+    let longest = ids.iter().map(|id| id.len()).max().unwrap_or(0);
+
+    for position in 0..longest {
+        let mut buckets: HashMap<Vec<&str>, &[&str]> = HashMap::new();
+
+        for id in ids.iter().filter(|id| id.len() > position) {
+            let masked = id[..position].iter().chain(id[position + 1..].iter()).cloned().collect::<Vec<&str>>();
+
+            match buckets.get(&masked) {
+                // Identical IDs share every bucket, so make sure these really differ once.
+                Some(other) => if let Some(common) = compare_ids(other, id) {
+                    return Some(common);
+                },
+                None => {
+                    buckets.insert(masked, id);
+                },
+            }
         }
     }
 
     None
 }
 
-fn compare_ids(line1: &String, line2: &String) -> Option<String> {
-    if line1.len() != line2.len() {
-        return None;
+/// Two IDs of the same length that differ in at most a given number of positions.
+#[derive(Debug, PartialEq, Eq)]
+struct SimilarPair {
+    first: String,
+    second: String,
+    /// Letter positions where the IDs differ.
+    mismatches: Vec<usize>,
+    /// The characters the IDs have in common, in order.
+    common: String,
+}
+
+/// Every pair of IDs whose Hamming distance is at most `distance`, in input order.
+fn find_similar_pairs(input: &str, distance: usize, letters: Letters) -> Vec<SimilarPair> {
+    let lines = input.lines().map(|id| letters.split(id)).collect::<Vec<Vec<&str>>>();
+    let mut result = Vec::new();
+
+    for (line1, line2) in unordered_pairs(&lines) {
+        if let Some(mismatches) = mismatch_positions(line1, line2) {
+            if mismatches.len() <= distance {
+                result.push(SimilarPair {
+                    first: line1.concat(),
+                    second: line2.concat(),
+                    mismatches,
+                    common: find_common_substring(line1, line2),
+                });
+            }
+        }
     }
 
-    let mut mismatches = 0;
+    result
+}
 
-    for (c1, c2) in line1.chars().zip(line2.chars()) {
-        if c1 != c2 {
-            mismatches += 1;
+/// IDs linked by chains of edits, each within the clustering threshold of the next.
+#[derive(Debug, PartialEq, Eq)]
+struct Cluster {
+    /// In input order.
+    ids: Vec<String>,
+    /// The per-position majority if every ID has the same length, otherwise the
+    /// medoid: the ID with the smallest total edit distance to the rest.
+    consensus: String,
+}
+
+/// Groups IDs whose Levenshtein distance is at most `threshold`, transitively.
+/// Clusters are ordered by their first ID in the input.
+fn cluster_ids(input: &str, threshold: usize, letters: Letters) -> Vec<Cluster> {
+    let ids = input.lines().map(|id| letters.split(id)).collect::<Vec<Vec<&str>>>();
+    let mut index = QGramIndex::new();
+    let mut sets = UnionFind::new(ids.len());
+
+    for (i, id) in ids.iter().enumerate() {
+        for j in index.candidates(id, threshold) {
+            if edit_distance_within(id, &ids[j], threshold).is_some() {
+                sets.union(i, j);
+            }
         }
+
+        index.insert(id);
     }
 
-    if mismatches == 1 {
-        Some(find_common_substring(line1, line2))
-    } else {
-        None
+    sets.groups()
+        .into_iter()
+        .map(|members| {
+            let members = members.iter().map(|&i| ids[i].as_slice()).collect::<Vec<&[&str]>>();
+            Cluster {
+                ids: members.iter().map(|id| id.concat()).collect(),
+                consensus: consensus(&members),
+            }
+        })
+        .collect()
+}
+
+fn consensus(ids: &[&[&str]]) -> String {
+    let length = ids[0].len();
+
+    if ids.iter().all(|id| id.len() == length) {
+        // Majority letter at each position; ties go to the letter seen first.
+        return (0..length)
+            .map(|position| {
+                let mut counts: Vec<(&str, usize)> = Vec::new();
+                for id in ids.iter() {
+                    match counts.iter_mut().find(|(letter, _)| *letter == id[position]) {
+                        Some((_, count)) => *count += 1,
+                        None => counts.push((id[position], 1)),
+                    }
+                }
+                counts.iter().rev().max_by_key(|(_, count)| *count).unwrap().0
+            })
+            .collect();
     }
+
+    ids.iter()
+        .rev()
+        .min_by_key(|id| ids.iter().map(|other| edit_distance(id, other)).sum::<usize>())
+        .unwrap()
+        .concat()
 }
 
-fn find_common_substring(line1: &String, line2: &String) -> String {
-    let mut result = String::new();
+/// Length of the q-grams used to filter clustering candidates.
+const QGRAM_LENGTH: usize = 2;
+
+/// An inverted index from q-grams to the IDs containing them. Two IDs within edit
+/// distance `k` of each other must share at least `max(|a|, |b|) - q + 1 - k * q`
+/// q-grams, since each edit destroys at most `q` of them, so most IDs can be ruled
+/// out without computing a distance.
+struct QGramIndex<'a> {
+    /// For each q-gram, the IDs containing it and how many times.
+    postings: HashMap<&'a [&'a str], Vec<(usize, usize)>>,
+    lengths: Vec<usize>,
+}
 
-    for (c1, c2) in line1.chars().zip(line2.chars()) {
-        if c1 == c2 {
-            result.push(c1);
+impl<'a> QGramIndex<'a> {
+    fn new() -> Self {
+        QGramIndex { postings: HashMap::new(), lengths: Vec::new() }
+    }
+
+    fn insert(&mut self, id: &'a [&'a str]) {
+        let index = self.lengths.len();
+        self.lengths.push(id.len());
+
+        for (gram, count) in qgram_counts(id) {
+            self.postings.entry(gram).or_default().push((index, count));
+        }
+    }
+
+    /// Indexed IDs that could be within `threshold` edits of `id`.
+    fn candidates(&self, id: &[&str], threshold: usize) -> Vec<usize> {
+        let length_matches = |j: &usize| self.lengths[*j].abs_diff(id.len()) <= threshold;
+
+        // The bound only uses `id`'s length, which is never more than the larger one.
+        let required = (id.len() + 1).saturating_sub(QGRAM_LENGTH + threshold * QGRAM_LENGTH);
+        if required == 0 {
+            return (0..self.lengths.len()).filter(length_matches).collect();
+        }
+
+        let mut shared = HashMap::new();
+        for (gram, count) in qgram_counts(id) {
+            for &(j, other) in self.postings.get(gram).into_iter().flatten() {
+                *shared.entry(j).or_insert(0) += count.min(other);
+            }
         }
+
+        let mut result = shared.into_iter()
+            .filter(|&(j, count)| count >= required && length_matches(&j))
+            .map(|(j, _)| j)
+            .collect::<Vec<usize>>();
+        result.sort_unstable();
+
+        result
+    }
+}
+
+fn qgram_counts<'a>(id: &'a [&'a str]) -> HashMap<&'a [&'a str], usize> {
+    let mut result = HashMap::new();
+
+    for gram in id.windows(QGRAM_LENGTH) {
+        *result.entry(gram).or_insert(0) += 1;
     }
 
     result
 }
 
-fn iterate_all_lines_against_each_other(input: &String) -> LineComparerIterator {
-    LineComparerIterator::new(input)
+fn edit_distance(a: &[&str], b: &[&str]) -> usize {
+    edit_distance_within(a, b, usize::MAX).unwrap()
+}
+
+/// Levenshtein distance between two split IDs, or `None` as soon as it's certain
+/// to be more than `limit`.
+fn edit_distance_within(a: &[&str], b: &[&str], limit: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > limit {
+        return None;
+    }
+
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, x) in a.iter().enumerate() {
+        current[0] = i + 1;
+
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + (x != y) as usize;
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        if current.iter().all(|&d| d > limit) {
+            return None;
+        }
+
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    Some(previous[b.len()]).filter(|&d| d <= limit)
 }
 
-struct LineComparerIterator {
-    lines: Vec<String>,
-    current_index: usize,
-    compare_index: usize,
+/// One step of turning one ID into another. Positions are letter positions in the
+/// ID being changed.
+#[derive(Debug, PartialEq, Eq)]
+enum Edit<'a> {
+    Substitute(usize, &'a str, &'a str),
+    Insert(usize, &'a str),
+    Delete(usize, &'a str),
 }
 
-impl LineComparerIterator {
-    fn new(string: &String) -> LineComparerIterator {
-        LineComparerIterator {
-            lines: string.clone().lines().map(|x| String::from(x)).collect(),
-            current_index: 0,
-            compare_index: 0,
+impl fmt::Display for Edit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Edit::Substitute(position, from, to) => write!(f, "{} -> {} at {}", from, to, position),
+            Edit::Insert(position, letter) => write!(f, "insert {} at {}", letter, position),
+            Edit::Delete(position, letter) => write!(f, "delete {} at {}", letter, position),
         }
     }
 }
 
-impl Iterator for LineComparerIterator {
-    type Item = (String, String);
+/// A shortest list of edits turning `from` into `to`, in position order.
+fn edit_script<'a>(from: &[&'a str], to: &[&'a str]) -> Vec<Edit<'a>> {
+    let mut table = vec![vec![0; to.len() + 1]; from.len() + 1];
 
-    fn next(&mut self) -> Option<(String, String)> {
-        self.compare_index += 1;
-        if self.compare_index >= self.lines.len() {
-            self.current_index += 1;
-            self.compare_index = self.current_index + 1;
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    table[0] = (0..=to.len()).collect();
+    for i in 1..=from.len() {
+        for j in 1..=to.len() {
+            let substitution = table[i - 1][j - 1] + (from[i - 1] != to[j - 1]) as usize;
+            table[i][j] = substitution.min(table[i - 1][j] + 1).min(table[i][j - 1] + 1);
         }
+    }
 
-        if self.current_index >= self.lines.len() - 1 {
-            None
+    let mut result = Vec::new();
+    let (mut i, mut j) = (from.len(), to.len());
+
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 && table[i][j] == table[i - 1][j - 1] + (from[i - 1] != to[j - 1]) as usize {
+            if from[i - 1] != to[j - 1] {
+                result.push(Edit::Substitute(i - 1, from[i - 1], to[j - 1]));
+            }
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && table[i][j] == table[i - 1][j] + 1 {
+            result.push(Edit::Delete(i - 1, from[i - 1]));
+            i -= 1;
         } else {
-            Some((self.lines[self.current_index].clone(), self.lines[self.compare_index].clone()))
+            result.push(Edit::Insert(i, to[j - 1]));
+            j -= 1;
+        }
+    }
+
+    result.reverse();
+    result
+}
+
+/// The original pairwise search, kept to check `calculate_similar` against.
+#[cfg(test)]
+fn calculate_similar_pairwise(input: &str) -> Option<String> {
+    let lines = input.lines().map(|id| Letters::Chars.split(id)).collect::<Vec<Vec<&str>>>();
+
+    for (line1, line2) in unordered_pairs(&lines) {
+        if let Some(matching) = compare_ids(line1, line2) {
+            return Some(matching)
+        }
+    }
+
+    None
+}
+
+fn compare_ids(line1: &[&str], line2: &[&str]) -> Option<String> {
+    match mismatch_positions(line1, line2) {
+        Some(ref mismatches) if mismatches.len() == 1 => Some(find_common_substring(line1, line2)),
+        _ => None,
+    }
+}
+
+/// Positions where two split IDs differ, or `None` if they don't have the same
+/// number of letters.
+fn mismatch_positions(line1: &[&str], line2: &[&str]) -> Option<Vec<usize>> {
+    if line1.len() != line2.len() {
+        return None;
+    }
+
+    let mut mismatches = Vec::new();
+
+    for (i, (c1, c2)) in line1.iter().zip(line2.iter()).enumerate() {
+        if c1 != c2 {
+            mismatches.push(i);
         }
     }
+
+    Some(mismatches)
+}
+
+fn find_common_substring(line1: &[&str], line2: &[&str]) -> String {
+    let mut result = String::new();
+
+    for (c1, c2) in line1.iter().zip(line2.iter()) {
+        if c1 == c2 {
+            result.push_str(c1);
+        }
+    }
+
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// The count for `letter`, or `None` if it doesn't appear.
+    fn count<'a>(counts: &'a LetterCounts, letter: &str) -> Option<&'a u32> {
+        match counts {
+            LetterCounts::Ascii(counts) => match letter.as_bytes() {
+                &[byte] => counts.get(byte as usize).filter(|&&c| c > 0),
+                _ => None,
+            },
+            LetterCounts::Other(counts) => counts.get(letter),
+        }
+    }
+
     #[test]
     fn test_calculate_checksums() {
-        let result = calculate_checksums(&String::from("abc\naabccc\naaabbc"));
+        let result = checksum_report("abc\naabccc\naaabbc", &[2, 3], Letters::Chars).product;
         assert_eq!(result, 4);
     }
 
     #[test]
     fn test_count_letters() {
-        let r1 = count_letters("aaabcc");
-        assert_eq!(r1.get(&"a".chars().next().unwrap()).unwrap(), &3);
-        assert_eq!(r1.get(&"b".chars().next().unwrap()).unwrap(), &1);
-        assert_eq!(r1.get(&"c".chars().next().unwrap()).unwrap(), &2);
+        let r1 = count_letters("aaabcc", Letters::Chars);
+        assert_eq!(count(&r1, "a").unwrap(), &3);
+        assert_eq!(count(&r1, "b").unwrap(), &1);
+        assert_eq!(count(&r1, "c").unwrap(), &2);
     }
 
     #[test]
     fn test_count_letters_check() {
-        let r1 = count_letters("aaabcc");
+        let r1 = count_letters("aaabcc", Letters::Chars);
 
         assert!(r1.values().any(|&x| x == 2));
         assert!(r1.values().any(|&x| x == 3));
     }
 
+    #[test]
+    fn test_count_letters_non_ascii() {
+        let r1 = count_letters("ééa", Letters::Chars);
+
+        assert_eq!(count(&r1, "é"), Some(&2));
+        assert_eq!(count(&r1, "a"), Some(&1));
+        assert_eq!(count(&r1, "b"), None);
+        assert_eq!(count(&count_letters("abc", Letters::Chars), "é"), None);
+    }
+
+    #[test]
+    fn test_checksum_report() {
+        let input = "abcdef\nbababc\nabbcde\nabcccd\naabcdd\nabcdee\nababab\naaaabb";
+        let report = checksum_report(input, &[2, 3, 4], Letters::Chars);
+
+        assert_eq!(15, report.product);
+        assert_eq!(vec!["bababc", "abbcde", "aabcdd", "abcdee", "aaaabb"], report.breakdown[0].1);
+        assert_eq!(vec!["bababc", "abcccd", "ababab"], report.breakdown[1].1);
+        assert_eq!((4, vec![String::from("aaaabb")]), report.breakdown[2]);
+    }
+
+    #[test]
+    fn test_parse_multiplicities() {
+        assert_eq!(vec![2, 3, 4], parse_multiplicities("2,3, 4").unwrap());
+        assert!(parse_multiplicities("2,x").is_err());
+        assert!(parse_multiplicities("0").is_err());
+    }
+
     #[test]
     fn test_calculate_similar() {
         let s = String::from("abcde\nfghij\nklmno\npqrst\nfguij\naxcye\nwvxyz");
-        let result = calculate_similar(&s);
+        let result = calculate_similar(&s, Letters::Chars);
         assert_eq!(result.unwrap(), String::from("fgij"));
     }
+
+    #[test]
+    fn test_find_similar_pairs() {
+        let s = String::from("abcde\nfghij\nklmno\npqrst\nfguij\naxcye\nwvxyz");
+
+        let pairs = find_similar_pairs(&s, 1, Letters::Chars);
+        assert_eq!(1, pairs.len());
+        assert_eq!(SimilarPair {
+            first: String::from("fghij"),
+            second: String::from("fguij"),
+            mismatches: vec![2],
+            common: String::from("fgij"),
+        }, pairs[0]);
+
+        let pairs = find_similar_pairs(&s, 2, Letters::Chars);
+        assert_eq!(2, pairs.len());
+        assert_eq!("abcde", pairs[0].first);
+        assert_eq!("axcye", pairs[0].second);
+        assert_eq!(vec![1, 3], pairs[0].mismatches);
+        assert_eq!("ace", pairs[0].common);
+    }
+
+    #[test]
+    fn test_find_similar_pairs_several_near_duplicates() {
+        let s = String::from("aaaa\naaab\nbaaa\nzzzz\naaaa");
+        let pairs = find_similar_pairs(&s, 1, Letters::Chars)
+            .into_iter()
+            .map(|p| (p.first, p.second, p.mismatches.len()))
+            .collect::<Vec<(String, String, usize)>>();
+
+        assert_eq!(vec![
+            (String::from("aaaa"), String::from("aaab"), 1),
+            (String::from("aaaa"), String::from("baaa"), 1),
+            (String::from("aaaa"), String::from("aaaa"), 0),
+            (String::from("aaab"), String::from("aaaa"), 1),
+            (String::from("baaa"), String::from("aaaa"), 1),
+        ], pairs);
+    }
+
+    #[test]
+    fn test_find_similar_pairs_short_inputs() {
+        assert!(find_similar_pairs("", 1, Letters::Chars).is_empty());
+        assert!(find_similar_pairs("abc", 1, Letters::Chars).is_empty());
+        assert_eq!(1, find_similar_pairs("abc\nabd", 1, Letters::Chars).len());
+    }
+
+    #[test]
+    fn test_calculate_similar_matches_pairwise() {
+        let input = String::from(include_str!("input.txt"));
+        let expected = calculate_similar_pairwise(&input);
+
+        assert!(expected.is_some());
+        assert_eq!(expected, calculate_similar(&input, Letters::Chars));
+    }
+
+    #[test]
+    fn test_calculate_similar_ignores_duplicates() {
+        assert_eq!(None, calculate_similar(&String::from("abcd\nabcd\nwxyz"), Letters::Chars));
+        assert_eq!(Some(String::from("abd")), calculate_similar(&String::from("abcd\nabcd\nabxd"), Letters::Chars));
+        assert_eq!(None, calculate_similar(&String::from("abc\nabcd"), Letters::Chars));
+    }
+
+    #[test]
+    fn test_non_ascii_ids_compared_by_char() {
+        // Same number of chars but different byte lengths.
+        let s = String::from("añb\naxb\nqqq");
+
+        assert_eq!(Some(String::from("ab")), calculate_similar(&s, Letters::Chars));
+        assert_eq!(Some(String::from("ab")), calculate_similar_pairwise(&s));
+
+        let pairs = find_similar_pairs(&String::from("héllo\nhello\nhällö"), 2, Letters::Chars);
+        assert_eq!(vec![1], pairs[0].mismatches);
+        assert_eq!("hllo", pairs[0].common);
+        assert_eq!(vec![1, 4], pairs[2].mismatches);
+        assert_eq!("hll", pairs[2].common);
+    }
+
+    #[test]
+    fn test_checksum_report_non_ascii() {
+        let report = checksum_report("ññx\nñxx\nüüü", &[2, 3], Letters::Chars);
+
+        assert_eq!(vec!["ññx", "ñxx"], report.breakdown[0].1);
+        assert_eq!(vec!["üüü"], report.breakdown[1].1);
+    }
+
+    #[test]
+    fn test_split_graphemes() {
+        let decomposed = "e\u{301}e\u{301}e";
+
+        assert_eq!(5, Letters::Chars.split(decomposed).len());
+        assert_eq!(vec!["e\u{301}", "e\u{301}", "e"], Letters::Graphemes.split(decomposed));
+        assert_eq!(2, Letters::Graphemes.split("\u{1F469}\u{200D}\u{1F4BB}a").len());
+        assert_eq!(vec!["\u{301}", "a"], Letters::Graphemes.split("\u{301}a"));
+        assert!(Letters::Graphemes.split("").is_empty());
+    }
+
+    #[test]
+    fn test_graphemes_option() {
+        let counts = count_letters("e\u{301}e\u{301}e", Letters::Graphemes);
+        assert_eq!(Some(&2), count(&counts, "e\u{301}"));
+        assert_eq!(Some(&1), count(&counts, "e"));
+        assert_eq!(Some(&3), count(&count_letters("e\u{301}e\u{301}e", Letters::Chars), "e"));
+
+        let s = String::from("e\u{301}x\nex");
+        assert_eq!(None, calculate_similar(&s, Letters::Chars));
+        assert_eq!(Some(String::from("x")), calculate_similar(&s, Letters::Graphemes));
+        assert_eq!(vec![0], find_similar_pairs(&s, 1, Letters::Graphemes)[0].mismatches);
+    }
+
+    fn split(id: &str) -> Vec<&str> {
+        Letters::Chars.split(id)
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(3, edit_distance(&split("kitten"), &split("sitting")));
+        assert_eq!(4, edit_distance(&split(""), &split("abcd")));
+        assert_eq!(Some(1), edit_distance_within(&split("abcd"), &split("abd"), 1));
+        assert_eq!(None, edit_distance_within(&split("kitten"), &split("sitting"), 2));
+        assert_eq!(None, edit_distance_within(&split("abcdef"), &split("ab"), 3));
+    }
+
+    #[test]
+    fn test_cluster_ids() {
+        let input = "abcdef\nabxdef\nbcdef\nzzzzzz\nabcdefg\nqrstuv";
+        let clusters = cluster_ids(input, 1, Letters::Chars);
+
+        assert_eq!(3, clusters.len());
+        assert_eq!(Cluster {
+            ids: vec![String::from("abcdef"), String::from("abxdef"), String::from("bcdef"), String::from("abcdefg")],
+            consensus: String::from("abcdef"),
+        }, clusters[0]);
+        assert_eq!(vec!["zzzzzz"], clusters[1].ids);
+        assert_eq!("zzzzzz", clusters[1].consensus);
+    }
+
+    #[test]
+    fn test_consensus_majority() {
+        let clusters = cluster_ids("abcd\nabce\nxbce\nabzd", 1, Letters::Chars);
+
+        assert_eq!(1, clusters.len());
+        assert_eq!("abcd", clusters[0].consensus);
+    }
+
+    #[test]
+    fn test_cluster_ids_matches_all_pairs() {
+        use crate::util::prop;
+
+        prop::check("day 2 q-gram clusters match all pairs", |rng| {
+            let ids = (0..rng.range(0, 30))
+                .map(|_| (0..rng.range(0, 8)).map(|_| *rng.choose(&['a', 'b', 'c'])).collect::<String>())
+                .collect::<Vec<String>>();
+            (ids, rng.range(0, 3) as usize)
+        }, |(ids, threshold)| {
+            let input = ids.join("\n");
+            let lines = input.lines().map(split).collect::<Vec<Vec<&str>>>();
+            let mut sets = UnionFind::new(lines.len());
+            for i in 0..lines.len() {
+                for j in 0..i {
+                    if edit_distance(&lines[i], &lines[j]) <= *threshold {
+                        sets.union(i, j);
+                    }
+                }
+            }
+
+            let expected = sets.groups()
+                .into_iter()
+                .map(|group| group.iter().map(|&i| lines[i].concat()).collect())
+                .collect::<Vec<Vec<String>>>();
+            let actual = cluster_ids(&input, *threshold, Letters::Chars)
+                .into_iter()
+                .map(|cluster| cluster.ids)
+                .collect::<Vec<Vec<String>>>();
+
+            actual == expected
+        });
+    }
+
+    #[test]
+    fn test_edit_script() {
+        let script = edit_script(&split("kitten"), &split("sitting"));
+
+        assert_eq!(vec![Edit::Substitute(0, "k", "s"), Edit::Substitute(4, "e", "i"), Edit::Insert(6, "g")], script);
+        assert_eq!(vec![Edit::Delete(1, "b")], edit_script(&split("abc"), &split("ac")));
+        assert!(edit_script(&split("abc"), &split("abc")).is_empty());
+        assert_eq!("k -> s at 0", script[0].to_string());
+    }
+
+    #[test]
+    fn test_index_nearest() {
+        let input = String::from(include_str!("input.txt"));
+        let index = index_ids(&input, Letters::Chars);
+        let ids = input.lines().map(String::from).collect::<Vec<String>>();
+
+        assert_eq!(Some((&ids[7], 0)), index.nearest(&ids[7], 0));
+        assert_eq!(Some((&ids[3], 1)), index.nearest(&format!("{}x", ids[3]), 2));
+        assert_eq!(None, index.nearest(&String::from("a"), 3));
+
+        let (near, distance) = index.nearest(&String::from("abc"), usize::MAX).unwrap();
+        let expected = ids.iter().map(|id| edit_distance(&split(id), &split("abc"))).min().unwrap();
+        assert_eq!(expected, distance);
+        assert_eq!(distance, edit_script(&split("abc"), &split(near)).len());
+    }
+
+    #[test]
+    fn test_generated_input_parses() {
+        let input = generate_input(200, &mut Rng::new(3));
+
+        assert_eq!(200, input.lines().count());
+        assert!(input.lines().all(|id| id.len() == 26));
+        assert_eq!(25, calculate_similar(&input, Letters::Chars).unwrap().len());
+    }
 }
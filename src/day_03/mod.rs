@@ -1,89 +1,905 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::io::prelude::*;
+
 use crate::util;
+use crate::util::graph::Graph;
+use crate::util::image::{self, Image};
+use crate::util::pairs::unordered_pairs;
+use crate::util::rng::Rng;
 
+/// The size of the puzzle's fabric, which generated claims stay within.
 const FABRIC_SIZE: usize = 1000;
 
 pub fn run_part_1(args: &[String]) {
-    util::run_part_n("3", args, find_overlaps);
+    util::run_part_n_with_options("3", args, find_overlaps);
 }
 
 pub fn run_part_2(args: &[String]) {
     util::run_part_n("3", args, find_standalone);
 }
 
-fn find_overlaps(filename: &String) -> util::AppResult {
+pub fn run_query(args: &[String]) {
+    util::run_part_n("3", args, query_claims);
+}
+
+pub fn run_stats(args: &[String]) {
+    util::run_part_n_with_options("3", args, print_stats);
+}
+
+pub fn run_live(args: &[String]) {
+    util::run_with_options("3", args, live_claims);
+}
+
+pub fn run_conflicts(args: &[String]) {
+    util::run_part_n_with_options("3", args, print_conflicts);
+}
+
+/// `size` claims that all fit within the fabric.
+pub fn generate_input(size: usize, rng: &mut Rng) -> String {
+    (1..=size)
+        .map(|id| {
+            let width = rng.range(1, 30);
+            let height = rng.range(1, 30);
+            let left = rng.range(0, FABRIC_SIZE as i64 - width);
+            let top = rng.range(0, FABRIC_SIZE as i64 - height);
+            format!("#{} @ {},{}: {}x{}", id, left, top, width, height)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn find_overlaps(filename: &String, options: &util::Options) -> util::AppResult {
     let claims = read_claims(filename)?;
-    let result = count_overlaps(&claims);
 
-    println!("Overlaps: {}", result);
+    // A fixed fabric size may clip claims, so only the grid can count it.
+    let fabric = if options.value("fabric-size").is_some() {
+        let (fabric, clipped) = fabric_from_options(&claims, options)?;
+        warn_clipped(&fabric, &clipped);
+        println!("Overlaps: {}", fabric.count_overused());
+        Some(fabric)
+    } else {
+        println!("Overlaps: {}", overlap_area(&claims));
+        None
+    };
 
-    Ok(())
+    if !options.flag("preview") && options.value("image").is_none() {
+        return Ok(());
+    }
+
+    let fabric = match fabric {
+        Some(fabric) => fabric,
+        None => Fabric::filled(Fabric::around(&claims)?, &claims),
+    };
+    let standalone = standalone_claims(&claims).iter().map(|c| &c.rect).collect::<Vec<&Rectangle>>();
+
+    if options.flag("preview") {
+        let columns = match options.value("preview") {
+            Some(_) => options.parse_value::<usize>("preview")?.unwrap_or(PREVIEW_COLUMNS),
+            None => PREVIEW_COLUMNS,
+        };
+        print!("{}", ascii_preview(&fabric, &standalone, columns.max(1)));
+    }
+
+    image::save_from_options(options, |_| fabric_image(&fabric, &standalone))
 }
 
-fn count_overlaps(claims: &Vec<Claim>) -> i32 {
-    let mut fabric = [InchState::Unused; FABRIC_SIZE * FABRIC_SIZE];
+/// The area covered by two or more claims, found by sweeping down the fabric.
+/// The distinct left and right edges split the x axis into columns; each claim's
+/// top edge adds one to the coverage of the columns it spans and its bottom edge
+/// takes it away again, so between consecutive edges the overlapping area is the
+/// width of the columns covered twice times the distance swept. This is O(n²) in
+/// the number of claims however large they are.
+fn overlap_area(claims: &[Claim]) -> u64 {
+    let rects = claims.iter().map(|c| &c.rect).filter(|r| !r.is_empty()).collect::<Vec<&Rectangle>>();
 
-    for claim in claims.iter() {
-        for (x, y) in claim.rect.iter() {
-            let new_val = match fabric[(y * FABRIC_SIZE + x)] {
-                InchState::Unused => InchState::Used,
-                _ => InchState::Overused
-            };
+    let mut xs = rects.iter().flat_map(|r| vec![r.left as i64, r.left as i64 + r.width as i64]).collect::<Vec<i64>>();
+    xs.sort_unstable();
+    xs.dedup();
+
+    // (y, change in coverage, first column, end column)
+    let mut events = Vec::with_capacity(rects.len() * 2);
+    for rect in rects.iter() {
+        let first = xs.binary_search(&(rect.left as i64)).unwrap();
+        let end = xs.binary_search(&(rect.left as i64 + rect.width as i64)).unwrap();
+        events.push((rect.top as i64, 1, first, end));
+        events.push((rect.top as i64 + rect.height as i64, -1, first, end));
+    }
+    events.sort_unstable();
+
+    let mut coverage = vec![0i32; xs.len().saturating_sub(1)];
+    // Claims can span the whole i32 range, so the area only fits once it's unsigned.
+    let mut area: u64 = 0;
+    let mut previous_y = events.first().map_or(0, |e| e.0);
 
-            fabric[y * FABRIC_SIZE + x] = new_val;
+    for (y, change, first, end) in events {
+        let overlapping = (0..coverage.len())
+            .filter(|&column| coverage[column] >= 2)
+            .map(|column| (xs[column + 1] - xs[column]) as u64)
+            .sum::<u64>();
+        area += overlapping * (y - previous_y) as u64;
+        previous_y = y;
+
+        for column in coverage[first..end].iter_mut() {
+            *column += change;
         }
     }
 
-    count_fabric_overused(&fabric)
+    area
+}
+
+/// The fabric is sized to fit every claim unless `--fabric-size <n>` fixes it at
+/// `n` x `n` inches. Claims outside a fixed fabric are an error, or are clipped
+/// given `--clip`, in which case their IDs are returned alongside the fabric.
+fn fabric_from_options(claims: &[Claim], options: &util::Options) -> util::AppResult<(Fabric, Vec<i32>)> {
+    let size = match options.parse_value::<usize>("fabric-size")? {
+        Some(size) => size,
+        None => return Ok((Fabric::filled(Fabric::around(claims)?, claims), Vec::new())),
+    };
+
+    let fabric = Fabric::with_size(size)?;
+    let clipped = claims.iter().filter(|c| !fabric.contains(&c.rect)).map(|c| c.id).collect::<Vec<i32>>();
+
+    if let (Some(id), false) = (clipped.first(), options.flag("clip")) {
+        return Err(util::AppError::AppError(format!("Claim #{} extends outside the {}x{} fabric", id, size, size)));
+    }
+
+    Ok((Fabric::filled(fabric, claims), clipped))
 }
 
-fn count_fabric_overused(state: &[InchState]) -> i32 {
-    let mut total_overused = 0;
-    for inch in state.iter() {
-        if *inch == InchState::Overused {
-            total_overused += 1;
+fn warn_clipped(fabric: &Fabric, clipped: &[i32]) {
+    for id in clipped.iter() {
+        println!("Warning: Claim #{} extends outside the {}x{} fabric, clipping it", id, fabric.width, fabric.height);
+    }
+}
+
+#[cfg(test)]
+fn count_overlaps(claims: &[Claim]) -> i32 {
+    Fabric::filled(Fabric::around(claims).unwrap(), claims).count_overused()
+}
+
+/// Reports how deeply the fabric is covered. The fabric is the claims' bounding box
+/// unless `--fabric-size` is given, and `--json` prints the statistics as JSON.
+fn print_stats(filename: &String, options: &util::Options) -> util::AppResult {
+    let claims = read_claims(filename)?;
+    let (fabric, clipped) = fabric_from_options(&claims, options)?;
+    let stats = fabric.statistics();
+
+    // Clipped claims go in the JSON so nothing else is printed before it.
+    if options.flag("json") {
+        println!("{}", stats.to_json(&clipped));
+        return Ok(());
+    }
+
+    warn_clipped(&fabric, &clipped);
+
+    println!("Fabric: {}x{} at {},{}", stats.width, stats.height, stats.left, stats.top);
+    println!("Unclaimed: {}", stats.unclaimed());
+    for (depth, area) in stats.histogram.iter().enumerate().skip(1) {
+        println!("Covered by exactly {} claims: {}", depth, area);
+    }
+    println!("Covered by 2 or more claims: {}", stats.overlapping());
+
+    if let Some((x, y)) = stats.deepest {
+        println!("Maximum depth {} first reached at {},{} ({} inches that deep)",
+            stats.max_depth, x, y, stats.histogram[stats.max_depth as usize]);
+    }
+
+    Ok(())
+}
+
+/// How deeply a fabric is covered by claims.
+struct CoverageStats {
+    left: i32,
+    top: i32,
+    width: usize,
+    height: usize,
+    /// The area covered by exactly `k` claims, at index `k`, up to the maximum depth.
+    histogram: Vec<i64>,
+    max_depth: u32,
+    /// The first inch, row by row, covered `max_depth` times. `None` for an empty fabric.
+    deepest: Option<(i32, i32)>,
+}
+
+impl CoverageStats {
+    fn unclaimed(&self) -> i64 {
+        self.histogram[0]
+    }
+
+    fn overlapping(&self) -> i64 {
+        self.histogram.iter().skip(2).sum()
+    }
+
+    /// `clipped` lists the claims cut off by a fixed fabric size.
+    fn to_json(&self, clipped: &[i32]) -> String {
+        let histogram = self.histogram.iter().map(|area| area.to_string()).collect::<Vec<String>>();
+        let clipped = clipped.iter().map(|id| id.to_string()).collect::<Vec<String>>();
+        let deepest = match self.deepest {
+            Some((x, y)) => format!("{{\"x\": {}, \"y\": {}}}", x, y),
+            None => String::from("null"),
+        };
+
+        format!(
+            "{{\"fabric\": {{\"left\": {}, \"top\": {}, \"width\": {}, \"height\": {}}}, \"unclaimed\": {}, \"overlapping\": {}, \"max_depth\": {}, \"max_depth_at\": {}, \"histogram\": [{}], \"clipped\": [{}]}}",
+            self.left, self.top, self.width, self.height, self.unclaimed(), self.overlapping(), self.max_depth, deepest, histogram.join(", "), clipped.join(", "))
+    }
+}
+
+/// A heat map of how many claims cover each inch. Unclaimed fabric is black, inches
+/// with a single claim are gray, and overlaps run from blue to red as more claims
+/// pile up. Standalone claims are outlined in white.
+fn fabric_image(fabric: &Fabric, standalone: &[&Rectangle]) -> Image {
+    let max = fabric.max_coverage().max(2) as i64;
+
+    Image::pixmap(fabric.width, fabric.height, |column, row| {
+        if fabric.on_outline(column, row, standalone) {
+            return [255, 255, 255];
+        }
+
+        match fabric.inches[row * fabric.width + column] {
+            0 => [0, 0, 0],
+            1 => [80, 80, 80],
+            coverage => image::heat_colour(coverage as i64, 1, max),
+        }
+    })
+}
+
+/// Default width of `--preview` in characters.
+const PREVIEW_COLUMNS: usize = 80;
+
+/// The fabric shrunk to fit in `columns` characters. Each character stands for a
+/// block of inches twice as tall as it is wide, and shows the most claims covering
+/// any inch in it: blank for none, `.` for one, `2`-`9` for overlaps and `+` for
+/// ten or more. Blocks on a standalone claim's outline are `#`.
+fn ascii_preview(fabric: &Fabric, standalone: &[&Rectangle], columns: usize) -> String {
+    let block_width = fabric.width.div_ceil(columns).max(1);
+    let block_height = block_width * 2;
+    let mut result = String::new();
+
+    for block_top in (0..fabric.height).step_by(block_height) {
+        for block_left in (0..fabric.width).step_by(block_width) {
+            let rows = block_top..(block_top + block_height).min(fabric.height);
+            let inches = rows.flat_map(|row| (block_left..(block_left + block_width).min(fabric.width)).map(move |column| (column, row)));
+
+            let mut deepest = 0;
+            let mut outline = false;
+            for (column, row) in inches {
+                deepest = deepest.max(fabric.inches[row * fabric.width + column]);
+                outline = outline || fabric.on_outline(column, row, standalone);
+            }
+
+            result.push(match (outline, deepest) {
+                (true, _) => '#',
+                (_, 0) => ' ',
+                (_, 1) => '.',
+                (_, 2..=9) => std::char::from_digit(deepest, 10).unwrap(),
+                _ => '+',
+            });
         }
+        result.push('\n');
     }
 
-    total_overused
+    result
 }
 
 fn find_standalone(filename: &String) -> util::AppResult {
     let claims = read_claims(filename)?;
 
+    for claim in standalone_claims(&claims) {
+        println!("Standalone fabric id: {}", claim.id);
+    }
+
+    Ok(())
+}
+
+/// Claims that don't overlap any other claim.
+fn standalone_claims(claims: &[Claim]) -> Vec<&Claim> {
+    let index = ClaimIndex::new(claims);
+
+    claims.iter()
+        .enumerate()
+        .filter(|(i, claim)| !index.overlaps_other(*i, &claim.rect))
+        .map(|(_, claim)| claim)
+        .collect()
+}
+
+/// Loads the claims, then answers queries from stdin: `x,y` lists the claims
+/// covering that inch and `x,y: wxh` the claims overlapping that rectangle.
+fn query_claims(filename: &String) -> util::AppResult {
+    let claims = read_claims(filename)?;
+    let index = ClaimIndex::new(&claims);
+
+    println!("Indexed {} claims, enter x,y or x,y: wxh", claims.len());
+
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let found = match parse_query(&line) {
+            Ok(Query::Point(x, y)) => index.at(x, y),
+            Ok(Query::Area(rect)) => index.intersecting(&rect),
+            Err(e) => {
+                println!("Invalid query {}: {}", line.trim(), e);
+                continue;
+            },
+        };
+
+        let ids = found.iter().map(|c| format!("#{}", c.id)).collect::<Vec<String>>();
+        match ids.len() {
+            0 => println!("No claims"),
+            n => println!("{} claims: {}", n, ids.join(", ")),
+        }
+    }
+
+    Ok(())
+}
+
+enum Query {
+    Point(i32, i32),
+    Area(Rectangle),
+}
+
+fn parse_query(line: &str) -> util::AppResult<Query> {
+    let mut parts = line.split(':');
+    let position = parts.next().unwrap_or("");
+    let (x, y) = match position.split_once(',') {
+        Some((x, y)) => (x.trim().parse::<i32>()?, y.trim().parse::<i32>()?),
+        None => return Err(util::AppError::AppError(String::from("expected x,y"))),
+    };
+
+    let size = match parts.next() {
+        Some(size) => size,
+        None => return Rectangle::new(x, y, 1, 1).map(|_| Query::Point(x, y)),
+    };
+
+    match size.split_once('x') {
+        Some((width, height)) if parts.next().is_none() => Ok(Query::Area(Rectangle::new(
+            x,
+            y,
+            width.trim().parse::<i32>()?,
+            height.trim().parse::<i32>()?,
+        )?)),
+        _ => Err(util::AppError::AppError(String::from("expected x,y: wxh"))),
+    }
+}
+
+/// Most rectangles a quadtree node holds before it splits.
+const QUADTREE_CAPACITY: usize = 8;
+const QUADTREE_MAX_DEPTH: usize = 16;
+
+/// A quadtree over the claims' rectangles, covering their bounding box. Each claim
+/// is stored in the smallest node that wholly contains it, so a query only visits
+/// the nodes its area touches.
+struct ClaimIndex<'a> {
+    claims: &'a [Claim],
+    root: QuadNode,
+}
+
+struct QuadNode {
+    bounds: Bounds,
+    /// Indices of the claims stored here.
+    items: Vec<usize>,
+    /// Either empty or the four quadrants.
+    children: Vec<QuadNode>,
+}
+
+/// A half-open area, in `i64` so splitting never overflows.
+#[derive(Copy, Clone)]
+struct Bounds {
+    left: i64,
+    top: i64,
+    right: i64,
+    bottom: i64,
+}
+
+impl Bounds {
+    fn contains(&self, rect: &Rectangle) -> bool {
+        self.left <= rect.left as i64 && self.top <= rect.top as i64 &&
+            rect.right() as i64 <= self.right && rect.bottom() as i64 <= self.bottom
+    }
+
+    fn overlaps(&self, rect: &Rectangle) -> bool {
+        !rect.is_empty() && (rect.left as i64) < self.right && rect.right() as i64 > self.left &&
+            (rect.top as i64) < self.bottom && rect.bottom() as i64 > self.top
+    }
+
+    fn quadrants(&self) -> Vec<Bounds> {
+        let middle_x = self.left + (self.right - self.left) / 2;
+        let middle_y = self.top + (self.bottom - self.top) / 2;
+
+        vec![
+            Bounds { left: self.left, top: self.top, right: middle_x, bottom: middle_y },
+            Bounds { left: middle_x, top: self.top, right: self.right, bottom: middle_y },
+            Bounds { left: self.left, top: middle_y, right: middle_x, bottom: self.bottom },
+            Bounds { left: middle_x, top: middle_y, right: self.right, bottom: self.bottom },
+        ]
+    }
+}
+
+/// The smallest area holding every claim.
+fn bounding_box(claims: &[Claim]) -> Bounds {
+    bounding_box_of(claims.iter().map(|c| &c.rect))
+}
+
+/// The smallest area holding every non-empty rectangle.
+fn bounding_box_of<'a>(rects: impl Iterator<Item = &'a Rectangle> + Clone) -> Bounds {
+    let rects = rects.filter(|r| !r.is_empty());
+
+    Bounds {
+        left: rects.clone().map(|r| r.left as i64).min().unwrap_or(0),
+        top: rects.clone().map(|r| r.top as i64).min().unwrap_or(0),
+        right: rects.clone().map(|r| r.right() as i64).max().unwrap_or(0),
+        bottom: rects.map(|r| r.bottom() as i64).max().unwrap_or(0),
+    }
+}
+
+impl<'a> ClaimIndex<'a> {
+    fn new(claims: &'a [Claim]) -> ClaimIndex<'a> {
+        let mut root = QuadNode { bounds: bounding_box(claims), items: Vec::new(), children: Vec::new() };
+
+        for (i, _) in claims.iter().enumerate().filter(|(_, c)| !c.rect.is_empty()) {
+            root.insert(i, claims, 0);
+        }
+
+        ClaimIndex { claims, root }
+    }
+
+    /// Claims covering the inch at `x`, `y`, in input order.
+    fn at(&self, x: i32, y: i32) -> Vec<&'a Claim> {
+        self.intersecting(&Rectangle { left: x, top: y, width: 1, height: 1 })
+    }
+
+    /// Claims overlapping `rect`, in input order.
+    fn intersecting(&self, rect: &Rectangle) -> Vec<&'a Claim> {
+        let mut found = Vec::new();
+        self.root.query(rect, self.claims, &mut found);
+        found.sort_unstable();
+
+        found.into_iter().map(|i| &self.claims[i]).collect()
+    }
+
+    /// Whether any claim other than the one at `index` overlaps `rect`, stopping at
+    /// the first one found.
+    fn overlaps_other(&self, index: usize, rect: &Rectangle) -> bool {
+        self.root.any(rect, self.claims, index)
+    }
+}
+
+impl QuadNode {
+    fn insert(&mut self, item: usize, claims: &[Claim], depth: usize) {
+        let rect = &claims[item].rect;
+
+        if let Some(child) = self.children.iter_mut().find(|c| c.bounds.contains(rect)) {
+            return child.insert(item, claims, depth + 1);
+        }
+
+        self.items.push(item);
+
+        let splittable = self.bounds.right - self.bounds.left > 1 || self.bounds.bottom - self.bounds.top > 1;
+        if self.children.is_empty() && self.items.len() > QUADTREE_CAPACITY && depth < QUADTREE_MAX_DEPTH && splittable {
+            self.children = self.bounds.quadrants()
+                .into_iter()
+                .map(|bounds| QuadNode { bounds, items: Vec::new(), children: Vec::new() })
+                .collect();
+
+            for item in std::mem::take(&mut self.items) {
+                self.insert(item, claims, depth);
+            }
+        }
+    }
+
+    /// Searches the smaller nodes first, since on crowded fabric a nearby small claim
+    /// is likely to be found long before a large node's list has been scanned.
+    fn any(&self, rect: &Rectangle, claims: &[Claim], except: usize) -> bool {
+        self.bounds.overlaps(rect) && (
+            self.children.iter().any(|child| child.any(rect, claims, except)) ||
+            self.items.iter().any(|&i| i != except && claims[i].rect.overlaps(rect)))
+    }
+
+    fn query(&self, rect: &Rectangle, claims: &[Claim], found: &mut Vec<usize>) {
+        if !self.bounds.overlaps(rect) {
+            return;
+        }
+
+        found.extend(self.items.iter().filter(|&&i| claims[i].rect.overlaps(rect)));
+
+        for child in self.children.iter() {
+            child.query(rect, claims, found);
+        }
+    }
+}
+
+/// Lists the claims each claim overlaps and the connected groups of claims.
+/// `--edges` prints one `first second area` line per overlapping pair instead, and
+/// `--dot` prints the conflict graph in Graphviz DOT format.
+fn print_conflicts(filename: &String, options: &util::Options) -> util::AppResult {
+    let claims = read_claims(filename)?;
+    let conflicts = find_conflicts(&claims);
+
+    if options.flag("dot") {
+        print!("{}", conflicts.graph.to_undirected_dot("conflicts", |a, b| Some(conflicts.area(*a, *b).to_string())));
+        return Ok(());
+    }
+
+    if options.flag("edges") {
+        for (a, b) in conflicts.graph.edges() {
+            println!("{} {} {}", a, b, conflicts.area(*a, *b));
+        }
+        return Ok(());
+    }
+
     for claim in claims.iter() {
-        if !overlaps_any(claim, claims.iter().filter(|c| c.id != claim.id)) {
-            println!("Standalone fabric id: {}", claim.id);
+        let others = conflicts.of(claim.id)
+            .iter()
+            .map(|(other, area)| format!("#{} ({})", other, area))
+            .collect::<Vec<String>>();
+
+        if !others.is_empty() {
+            println!("#{} conflicts with {}", claim.id, others.join(", "));
         }
     }
 
+    let components = conflicts.graph.connected_components();
+    let (groups, alone): (Vec<&Vec<i32>>, Vec<&Vec<i32>>) = components.iter().partition(|c| c.len() > 1);
+    println!("{} groups of conflicting claims, {} claims conflict with nothing", groups.len(), alone.len());
+
+    for group in groups.iter() {
+        let ids = group.iter().map(|id| format!("#{}", id)).collect::<Vec<String>>();
+        println!("  {} claims: {}", group.len(), ids.join(", "));
+    }
+
     Ok(())
 }
 
-fn overlaps_any<'a>(claim: &Claim, rest: impl Iterator<Item = &'a Claim>) -> bool {
-    for compare in rest {
-        if claim.rect.overlaps(&compare.rect) {
-            return true;
+/// Which claims overlap which, and by how much.
+struct Conflicts {
+    /// A node for every claim ID and an edge from the earlier of each overlapping
+    /// pair of claims to the later one.
+    graph: Graph<i32>,
+    areas: HashMap<(i32, i32), i64>,
+}
+
+impl Conflicts {
+    /// The area two claims share, zero if they don't overlap.
+    fn area(&self, a: i32, b: i32) -> i64 {
+        self.areas.get(&(a.min(b), a.max(b))).cloned().unwrap_or(0)
+    }
+
+    /// The claims overlapping claim `id`, with the area they share, ordered by ID.
+    fn of(&self, id: i32) -> Vec<(i32, i64)> {
+        let mut result = self.graph.successors(&id)
+            .into_iter()
+            .chain(self.graph.predecessors(&id))
+            .map(|&other| (other, self.area(id, other)))
+            .collect::<Vec<(i32, i64)>>();
+        result.sort_unstable();
+
+        result
+    }
+}
+
+fn find_conflicts(claims: &[Claim]) -> Conflicts {
+    let mut graph = Graph::new();
+    let mut areas = HashMap::new();
+
+    for claim in claims.iter() {
+        graph.add_node(claim.id);
+    }
+
+    for (a, b) in unordered_pairs(claims) {
+        if let Some(shared) = a.rect.intersection(&b.rect) {
+            graph.add_edge(a.id, b.id);
+            areas.insert((a.id.min(b.id), a.id.max(b.id)), shared.area());
         }
     }
 
-    return false;
+    Conflicts { graph, areas }
 }
 
 fn read_claims(filename: &String) -> Result<Vec<Claim>, util::AppError> {
     let contents = util::read_file_input(filename)?;
-    let result = parse_claims(&contents);
-    Ok(result)
+    parse_claims(&contents)
 }
 
-fn parse_claims(input: &str) -> Vec<Claim> {
+fn parse_claims(input: &str) -> util::AppResult<Vec<Claim>> {
     input.lines().map(Claim::parse).collect()
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
-enum InchState {
-    Unused,
-    Used,
-    Overused,
+/// Manages a changing set of claims from commands on stdin, starting with the claims
+/// in `--load <file>` if given. Commands are `add <claim>` (or just the claim, in
+/// the puzzle's format), `remove <id>` and `standalone` to list every standalone
+/// claim. Every change reports the overlapping area and the standalone claims.
+fn live_claims(options: &util::Options) -> util::AppResult {
+    let mut live = LiveClaims::new();
+
+    if let Some(filename) = options.value("load") {
+        for claim in read_claims(filename)? {
+            live.add(claim)?;
+        }
+        println!("Loaded {} claims. {}", live.claims.len(), live.summary());
+    }
+
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        let command = line.trim();
+
+        let result = match command.split_once(' ') {
+            _ if command.is_empty() => continue,
+            _ if command == "standalone" => {
+                let ids = live.standalone().iter().map(|id| format!("#{}", id)).collect::<Vec<String>>();
+                println!("{} standalone claims: {}", ids.len(), ids.join(", "));
+                continue;
+            },
+            Some(("remove", id)) => parse_claim_id(id).and_then(|id| live.remove(id)),
+            Some(("add", claim)) => Claim::parse(claim.trim()).and_then(|claim| live.add(claim)),
+            _ if command.starts_with('#') => Claim::parse(command).and_then(|claim| live.add(claim)),
+            _ => Err(util::AppError::AppError(String::from("expected add <claim>, remove <id> or standalone"))),
+        };
+
+        match result {
+            Ok(()) => println!("{}", live.summary()),
+            Err(e) => println!("Invalid command {}: {}", command, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// A claim ID with or without its leading `#`.
+fn parse_claim_id(input: &str) -> util::AppResult<i32> {
+    let input = input.trim();
+    Ok(input.strip_prefix('#').unwrap_or(input).parse::<i32>()?)
+}
+
+/// Most standalone claims listed after each change.
+const LIVE_STANDALONE_SHOWN: usize = 10;
+
+/// A set of claims that changes over time. The fabric's coverage counts give the
+/// overlapping area as each inch crosses between one and two claims, and each claim
+/// keeps a count of the claims it overlaps so the standalone set is known without
+/// comparing every pair again.
+struct LiveClaims {
+    fabric: Fabric,
+    claims: BTreeMap<i32, Rectangle>,
+    /// For each claim, how many other claims it overlaps.
+    conflicts: BTreeMap<i32, usize>,
+    overlaps: i64,
+}
+
+impl LiveClaims {
+    fn new() -> LiveClaims {
+        LiveClaims {
+            fabric: Fabric::new(0, 0, 0, 0),
+            claims: BTreeMap::new(),
+            conflicts: BTreeMap::new(),
+            overlaps: 0,
+        }
+    }
+
+    fn add(&mut self, claim: Claim) -> util::AppResult {
+        if self.claims.contains_key(&claim.id) {
+            return Err(util::AppError::AppError(format!("Claim #{} already exists", claim.id)));
+        }
+
+        // Empty claims always fit, so they never move the fabric.
+        if !self.fabric.contains(&claim.rect) {
+            self.fabric = self.refit(&claim.rect)?;
+        }
+
+        let mut conflicts = 0;
+        for (id, rect) in self.claims.iter() {
+            if rect.overlaps(&claim.rect) {
+                *self.conflicts.get_mut(id).unwrap() += 1;
+                conflicts += 1;
+            }
+        }
+
+        self.overlaps += self.fabric.add(&claim.rect);
+        self.conflicts.insert(claim.id, conflicts);
+        self.claims.insert(claim.id, claim.rect);
+
+        Ok(())
+    }
+
+    fn remove(&mut self, id: i32) -> util::AppResult {
+        let rect = match self.claims.remove(&id) {
+            Some(rect) => rect,
+            None => return Err(util::AppError::AppError(format!("No claim #{}", id))),
+        };
+
+        self.conflicts.remove(&id);
+        for (other, other_rect) in self.claims.iter() {
+            if other_rect.overlaps(&rect) {
+                *self.conflicts.get_mut(other).unwrap() -= 1;
+            }
+        }
+
+        self.overlaps -= self.fabric.remove(&rect);
+
+        Ok(())
+    }
+
+    /// A new fabric just big enough for the current claims and `rect`, so space left
+    /// by withdrawn claims is given back. Fails if it would be too large.
+    fn refit(&self, rect: &Rectangle) -> util::AppResult<Fabric> {
+        let mut fabric = Fabric::covering(bounding_box_of(self.claims.values().chain(std::iter::once(rect))))?;
+
+        for rect in self.claims.values() {
+            fabric.add(rect);
+        }
+
+        Ok(fabric)
+    }
+
+    /// IDs of the claims that overlap no other claim, in order.
+    fn standalone(&self) -> Vec<i32> {
+        self.conflicts.iter().filter(|(_, &n)| n == 0).map(|(&id, _)| id).collect()
+    }
+
+    fn summary(&self) -> String {
+        let standalone = self.standalone();
+        let mut shown = standalone.iter().take(LIVE_STANDALONE_SHOWN).map(|id| format!("#{}", id)).collect::<Vec<String>>();
+        if standalone.len() > LIVE_STANDALONE_SHOWN {
+            shown.push(format!("and {} more", standalone.len() - LIVE_STANDALONE_SHOWN));
+        }
+
+        format!("Overlaps: {}, standalone: {}", self.overlaps, if shown.is_empty() { String::from("none") } else { shown.join(", ") })
+    }
+}
+
+/// The most square inches a fabric may have, 100MB of coverage counts.
+const MAX_FABRIC_AREA: usize = 25_000_000;
+
+/// How many claims cover each square inch of a rectangular area of fabric, stored
+/// row by row on the heap.
+struct Fabric {
+    left: i32,
+    top: i32,
+    width: usize,
+    height: usize,
+    inches: Vec<u32>,
+}
+
+impl Fabric {
+    fn new(left: i32, top: i32, width: usize, height: usize) -> Fabric {
+        Fabric { left, top, width, height, inches: vec![0; width * height] }
+    }
+
+    /// Like `new`, but fails rather than allocating more than `MAX_FABRIC_AREA` inches.
+    fn checked(left: i32, top: i32, width: usize, height: usize) -> util::AppResult<Fabric> {
+        match width.checked_mul(height) {
+            Some(area) if area <= MAX_FABRIC_AREA => Ok(Fabric::new(left, top, width, height)),
+            _ => Err(util::AppError::AppError(format!("A {}x{} fabric is too large to hold in memory", width, height))),
+        }
+    }
+
+    /// `size` x `size` inches with its top left corner at 0,0.
+    fn with_size(size: usize) -> util::AppResult<Fabric> {
+        Fabric::checked(0, 0, size, size)
+    }
+
+    /// The smallest fabric that holds every claim.
+    fn around(claims: &[Claim]) -> util::AppResult<Fabric> {
+        Fabric::covering(bounding_box(claims)).map_err(|e| util::AppError::AppError(format!("{}, use --fabric-size to set a smaller one", e)))
+    }
+
+    fn covering(b: Bounds) -> util::AppResult<Fabric> {
+        Fabric::checked(b.left as i32, b.top as i32, (b.right - b.left) as usize, (b.bottom - b.top) as usize)
+    }
+
+    /// `fabric` with every claim added.
+    fn filled(mut fabric: Fabric, claims: &[Claim]) -> Fabric {
+        for claim in claims.iter() {
+            fabric.add(&claim.rect);
+        }
+
+        fabric
+    }
+
+    fn contains(&self, rect: &Rectangle) -> bool {
+        rect.is_empty() || (rect.left >= self.left && rect.top >= self.top &&
+            rect.right() as i64 <= self.left as i64 + self.width as i64 &&
+            rect.bottom() as i64 <= self.top as i64 + self.height as i64)
+    }
+
+    /// Index of the inch at `x`, `y`, if it is on the fabric.
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        let column = (x as i64 - self.left as i64) as usize;
+        let row = (y as i64 - self.top as i64) as usize;
+
+        if x >= self.left && y >= self.top && column < self.width && row < self.height {
+            Some(row * self.width + column)
+        } else {
+            None
+        }
+    }
+
+    /// Adds one to the coverage of the inches `rect` covers, returning how many of them
+    /// became overused. Any part of it off the fabric is ignored.
+    fn add(&mut self, rect: &Rectangle) -> i64 {
+        let mut overused = 0;
+
+        for (x, y) in self.clip(rect).iter().flat_map(|r| r.iter()) {
+            if let Some(i) = self.index(x, y) {
+                self.inches[i] += 1;
+                if self.inches[i] == 2 {
+                    overused += 1;
+                }
+            }
+        }
+
+        overused
+    }
+
+    /// The part of `rect` on the fabric, so huge claims cost no more than the fabric.
+    fn clip(&self, rect: &Rectangle) -> Option<Rectangle> {
+        // The fabric's area is capped, so its sides always fit in an `i32`.
+        rect.intersection(&Rectangle { left: self.left, top: self.top, width: self.width as i32, height: self.height as i32 })
+    }
+
+    /// Takes a claim added with `add` away again, returning how many inches are no
+    /// longer overused.
+    fn remove(&mut self, rect: &Rectangle) -> i64 {
+        let mut freed = 0;
+
+        for (x, y) in self.clip(rect).iter().flat_map(|r| r.iter()) {
+            if let Some(i) = self.index(x, y) {
+                self.inches[i] -= 1;
+                if self.inches[i] == 1 {
+                    freed += 1;
+                }
+            }
+        }
+
+        freed
+    }
+
+    /// Inches covered by two or more claims.
+    fn count_overused(&self) -> i32 {
+        self.statistics().overlapping() as i32
+    }
+
+    fn statistics(&self) -> CoverageStats {
+        let mut histogram = vec![0; 1];
+        let mut max_depth = 0;
+        let mut deepest = None;
+
+        for (i, &depth) in self.inches.iter().enumerate() {
+            if depth as usize >= histogram.len() {
+                histogram.resize(depth as usize + 1, 0);
+            }
+            histogram[depth as usize] += 1;
+
+            if deepest.is_none() || depth > max_depth {
+                max_depth = depth;
+                deepest = Some((self.left + (i % self.width) as i32, self.top + (i / self.width) as i32));
+            }
+        }
+
+        CoverageStats { left: self.left, top: self.top, width: self.width, height: self.height, histogram, max_depth, deepest }
+    }
+
+    fn max_coverage(&self) -> u32 {
+        self.inches.iter().cloned().max().unwrap_or(0)
+    }
+
+    /// Whether the inch at `column`, `row` of the fabric is on the edge of any of `rects`.
+    fn on_outline(&self, column: usize, row: usize, rects: &[&Rectangle]) -> bool {
+        let x = self.left as i64 + column as i64;
+        let y = self.top as i64 + row as i64;
+
+        rects.iter().any(|r| {
+            let (left, top) = (r.left as i64, r.top as i64);
+            let (right, bottom) = (left + r.width as i64 - 1, top + r.height as i64 - 1);
+            let inside = (left..=right).contains(&x) && (top..=bottom).contains(&y);
+
+            inside && (x == left || x == right || y == top || y == bottom)
+        })
+    }
 }
 
 struct Claim {
@@ -92,17 +908,15 @@ struct Claim {
 }
 
 impl Claim {
-    fn parse(input: &str) -> Claim {
-        let mut parts = input.split(" @ ");
-        let id = parts.next().expect("Empty claim text");
-        let id = id.chars().skip(1).collect::<String>().parse::<i32>().expect("Could not determine claim ID");
-
-        let rect = Rectangle::parse(parts.next().expect("Could not find rectangle part of claim"));
+    fn parse(input: &str) -> util::AppResult<Claim> {
+        let invalid = || util::AppError::AppError(format!("Invalid claim: {}", input));
+        let (id, rect) = input.split_once(" @ ").ok_or_else(invalid)?;
+        let id = id.strip_prefix('#').ok_or_else(invalid)?.parse::<i32>()?;
 
-        Claim {
-            id: id,
-            rect: rect
-        }
+        Ok(Claim {
+            id,
+            rect: Rectangle::parse(rect)?,
+        })
     }
 }
 
@@ -114,7 +928,7 @@ struct Rectangle {
 }
 
 impl Rectangle {
-    fn parse(input: &str) -> Rectangle {
+    fn parse(input: &str) -> util::AppResult<Rectangle> {
         let mut parts = Vec::new();
         let mut current = String::new();
         for c in input.chars() {
@@ -123,79 +937,120 @@ impl Rectangle {
             }
 
             if c == ',' || c == ':' || c == 'x' {
-                parts.push(current.parse::<i32>().expect("Invalid rect definition"));
+                parts.push(current.parse::<i32>()?);
                 current = String::new();
             } else {
                 current.push(c);
             }
         }
-        parts.push(current.parse::<i32>().expect("Invalid rect definition"));
+        parts.push(current.parse::<i32>()?);
 
         if parts.len() != 4 {
-            panic!("Invalid rect definition");
+            return Err(util::AppError::AppError(format!("Invalid rect definition: {}", input)));
+        }
+
+        Rectangle::new(parts[0], parts[1], parts[2], parts[3])
+    }
+
+    /// Fails if the right or bottom edge is past `i32::MAX`.
+    fn new(left: i32, top: i32, width: i32, height: i32) -> util::AppResult<Rectangle> {
+        if left.checked_add(width).is_none() || top.checked_add(height).is_none() {
+            return Err(util::AppError::AppError(format!("Rectangle {},{}: {}x{} extends past the largest coordinate", left, top, width, height)));
+        }
+
+        Ok(Rectangle { left, top, width, height })
+    }
+
+    fn right(&self) -> i32 {
+        self.left + self.width
+    }
+
+    fn bottom(&self) -> i32 {
+        self.top + self.height
+    }
+
+    fn is_empty(&self) -> bool {
+        self.width <= 0 || self.height <= 0
+    }
+
+    fn area(&self) -> i64 {
+        if self.is_empty() { 0 } else { self.width as i64 * self.height as i64 }
+    }
+
+    /// The rectangle both cover, if they overlap.
+    fn intersection(&self, other: &Rectangle) -> Option<Rectangle> {
+        if !self.overlaps(other) {
+            return None;
         }
 
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
 
-        Rectangle { left: parts[0], top: parts[1], width: parts[2], height: parts[3]}
+        Some(Rectangle {
+            left,
+            top,
+            width: self.right().min(other.right()) - left,
+            height: self.bottom().min(other.bottom()) - top,
+        })
     }
 
-    fn iter(&self) -> RectPointIterator {
+    fn iter(&self) -> RectPointIterator<'_> {
         RectPointIterator::new(self)
     }
 
     fn overlaps(&self, other: &Rectangle) -> bool {
-        let self_right = self.left + self.width;
-        let other_right = other.left + other.width;
-        let self_bottom = self.top + self.height;
-        let other_bottom = other.top + other.height;
-
-        self.left < other_right && self_right > other.left &&
-            self.top < other_bottom && self_bottom > other.top
+        !self.is_empty() && !other.is_empty() &&
+            self.left < other.right() && self.right() > other.left &&
+            self.top < other.bottom() && self.bottom() > other.top
     }
 }
 
+/// Every inch in a rectangle as `(x, y)`, row by row.
 struct RectPointIterator<'a> {
-    position: i32,
+    x: i32,
+    y: i32,
     rect: &'a Rectangle,
 }
 
 impl<'a> RectPointIterator<'a> {
-    fn new(rect: &'a Rectangle) -> RectPointIterator {
+    fn new(rect: &'a Rectangle) -> RectPointIterator<'a> {
         RectPointIterator {
-            position: -1,
-            rect: rect,
+            x: rect.left,
+            y: if rect.is_empty() { rect.bottom() } else { rect.top },
+            rect,
         }
     }
 }
 
 impl<'a> Iterator for RectPointIterator<'a> {
-    type Item = (usize, usize);
-
-    fn next(&mut self) -> Option<(usize, usize)> {
-        self.position += 1;
+    type Item = (i32, i32);
 
-        if self.position >= self.rect.width * self.rect.height {
-            return None
+    fn next(&mut self) -> Option<(i32, i32)> {
+        if self.y >= self.rect.bottom() {
+            return None;
         }
 
-        let x = self.rect.left + (self.position % self.rect.width as i32);
-        let y = self.rect.top + (self.position / self.rect.width as i32);
+        let result = (self.x, self.y);
 
-        if y as usize >= FABRIC_SIZE {
-            None
-        } else {
-            Some((x as usize, y as usize))
+        self.x += 1;
+        if self.x >= self.rect.right() {
+            self.x = self.rect.left;
+            self.y += 1;
         }
+
+        Some(result)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::util::prop;
+    use crate::util::rng::Rng;
 
     #[test]
     fn test_claim_parsing() {
-        let claim = Claim::parse("#1 @ 829,837: 11x22");
+        let claim = Claim::parse("#1 @ 829,837: 11x22").unwrap();
 
         assert_eq!(claim.id, 1);
         assert_eq!(claim.rect.left, 829);
@@ -208,8 +1063,407 @@ mod tests {
     fn test_count_overlaps() {
         let claims = parse_claims("#1 @ 1,3: 4x4
 #2 @ 3,1: 4x4
-#3 @ 5,5: 2x2");
+#3 @ 5,5: 2x2").unwrap();
 
         assert_eq!(count_overlaps(&claims), 4);
     }
+
+    #[test]
+    fn test_rect_iter() {
+        let rect = Rectangle { left: 2, top: 5, width: 2, height: 3 };
+
+        assert_eq!(vec![(2, 5), (3, 5), (2, 6), (3, 6), (2, 7), (3, 7)], rect.iter().collect::<Vec<(i32, i32)>>());
+        assert_eq!(0, Rectangle { left: 0, top: 0, width: 0, height: 4 }.iter().count());
+        assert_eq!(0, Rectangle { left: 0, top: 0, width: 3, height: -1 }.iter().count());
+    }
+
+    #[test]
+    fn test_fabric_fits_claims() {
+        let claims = parse_claims("#1 @ 1500,2000: 3x2\n#2 @ 1502,2001: 2x2\n#3 @ 1200,2100: 1x1").unwrap();
+        let fabric = Fabric::around(&claims).unwrap();
+
+        assert_eq!((1200, 2000, 304, 101), (fabric.left, fabric.top, fabric.width, fabric.height));
+        assert_eq!(1, count_overlaps(&claims));
+        assert_eq!(0, count_overlaps(&[]));
+    }
+
+    #[test]
+    fn test_fabric_too_large() {
+        let claims = parse_claims("#1 @ 0,0: 2x2\n#2 @ 1000000,1000000: 2x2").unwrap();
+        let options = util::Options::parse(&[]).unwrap();
+
+        assert!(Fabric::around(&claims).is_err());
+        assert!(fabric_from_options(&claims, &options).is_err());
+        assert!(Fabric::with_size(1_000_000).is_err());
+
+        let huge = parse_claims("#1 @ 0,0: 2000000000x2000000000\n#2 @ 1,1: 2x2").unwrap();
+        let (clipped, _) = fabric_from_options(&huge, &util::Options::parse(&[String::from("--fabric-size"), String::from("3"), String::from("--clip")]).unwrap()).unwrap();
+        assert_eq!(4, clipped.count_overused());
+        assert_eq!(0, overlap_area(&claims));
+    }
+
+    #[test]
+    fn test_rectangle_overflow() {
+        assert!(Claim::parse("#1 @ 2000000000,0: 500000000x1").is_err());
+        assert!(Claim::parse("#1 @ 0,2147483647: 1x1").is_err());
+        assert!(Claim::parse("#1 @ 2147483646,0: 1x1").is_ok());
+        assert!(parse_query("2147483647,0").is_err());
+        assert!(parse_query("0,0: 1x2147483647").is_ok());
+    }
+
+    #[test]
+    fn test_fabric_size_option() {
+        let claims = parse_claims("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2").unwrap();
+        let options = |args: &[&str]| util::Options::parse(&args.iter().map(|a| a.to_string()).collect::<Vec<String>>()).unwrap();
+
+        let (fabric, clipped) = fabric_from_options(&claims, &options(&["--fabric-size", "7"])).unwrap();
+        assert!(clipped.is_empty());
+        assert_eq!(4, fabric.count_overused());
+        assert!(fabric_from_options(&claims, &options(&["--fabric-size", "6"])).is_err());
+
+        let (fabric, clipped) = fabric_from_options(&claims, &options(&["--fabric-size", "4", "--clip"])).unwrap();
+        assert_eq!(16, fabric.inches.len());
+        assert_eq!(1, fabric.count_overused());
+        assert_eq!(vec![1, 2, 3], clipped);
+        assert!(fabric.statistics().to_json(&clipped).ends_with("\"clipped\": [1, 2, 3]}"));
+    }
+
+    #[test]
+    fn test_overlap_area() {
+        let claims = parse_claims("#1 @ 1,3: 4x4
+#2 @ 3,1: 4x4
+#3 @ 5,5: 2x2").unwrap();
+
+        assert_eq!(4, overlap_area(&claims));
+        assert_eq!(0, overlap_area(&[]));
+    }
+
+    #[test]
+    fn test_overlap_area_matches_grid_on_input() {
+        let claims = parse_claims(include_str!("input.txt")).unwrap();
+
+        assert_eq!(count_overlaps(&claims) as u64, overlap_area(&claims));
+    }
+
+    #[test]
+    fn test_overlap_area_large_claims() {
+        let claims = parse_claims("#1 @ 0,0: 1000000000x1000000000\n#2 @ 500000000,0: 1000000000x1000000000\n#3 @ 0,999999999: 2x2").unwrap();
+
+        assert_eq!(500_000_000 * 1_000_000_000 + 2, overlap_area(&claims));
+
+        // Every quadrant of the i32 plane claimed twice, an area past i64::MAX.
+        let quadrants = [(i32::MIN, i32::MIN), (0, i32::MIN), (i32::MIN, 0), (0, 0)];
+        let input = quadrants.iter().chain(quadrants.iter()).enumerate()
+            .map(|(i, (x, y))| format!("#{} @ {},{}: {}x{}", i + 1, x, y, i32::MAX, i32::MAX))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let claims = parse_claims(&input).unwrap();
+
+        assert_eq!(4 * (i32::MAX as u64 * i32::MAX as u64), overlap_area(&claims));
+    }
+
+    #[test]
+    fn test_overlap_area_matches_grid() {
+        prop::check("overlap_area matches the grid", random_claims, |claims| {
+            let claims = to_claims(claims);
+            overlap_area(&claims) == count_overlaps(&claims) as u64
+        });
+    }
+
+    fn example_fabric() -> (Vec<Claim>, Fabric) {
+        let claims = parse_claims("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2").unwrap();
+        let fabric = Fabric::filled(Fabric::around(&claims).unwrap(), &claims);
+        (claims, fabric)
+    }
+
+    #[test]
+    fn test_coverage_counts() {
+        let claims = parse_claims("#1 @ 0,0: 3x3\n#2 @ 1,1: 2x2\n#3 @ 2,2: 1x1").unwrap();
+        let fabric = Fabric::filled(Fabric::around(&claims).unwrap(), &claims);
+
+        assert_eq!(vec![1, 1, 1, 1, 2, 2, 1, 2, 3], fabric.inches);
+        assert_eq!(3, fabric.max_coverage());
+        assert_eq!(4, fabric.count_overused());
+    }
+
+    #[test]
+    fn test_ascii_preview() {
+        let (claims, fabric) = example_fabric();
+        let standalone = standalone_claims(&claims).iter().map(|c| &c.rect).collect::<Vec<&Rectangle>>();
+
+        assert_eq!("  ....\n..22..\n....##\n", ascii_preview(&fabric, &standalone, 6));
+        assert_eq!(".2.\n..#\n", ascii_preview(&fabric, &standalone, 3));
+    }
+
+    #[test]
+    fn test_fabric_image() {
+        let (claims, fabric) = example_fabric();
+        let standalone = standalone_claims(&claims).iter().map(|c| &c.rect).collect::<Vec<&Rectangle>>();
+
+        let mut bytes = Vec::new();
+        fabric_image(&fabric, &standalone).write(&mut bytes, image::Format::Ppm).unwrap();
+        let header = b"P6\n6 6\n255\n".len();
+        let pixel = |x: usize, y: usize| &bytes[header + (y * 6 + x) * 3..header + (y * 6 + x) * 3 + 3];
+
+        assert_eq!(&[0, 0, 0], pixel(0, 0));
+        assert_eq!(&[80, 80, 80], pixel(2, 0));
+        assert_eq!(&[255, 0, 0], pixel(2, 2));
+        assert_eq!(&[255, 255, 255], pixel(5, 5));
+    }
+
+    #[test]
+    fn test_find_conflicts() {
+        let claims = parse_claims("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n#4 @ 4,6: 3x1\n#5 @ 20,20: 1x1").unwrap();
+        let conflicts = find_conflicts(&claims);
+
+        assert_eq!(vec![(2, 4), (4, 1)], conflicts.of(1));
+        assert_eq!(vec![(1, 1), (3, 2)], conflicts.of(4));
+        assert!(conflicts.of(5).is_empty());
+        assert_eq!(4, conflicts.area(2, 1));
+        assert_eq!(0, conflicts.area(2, 3));
+        assert_eq!(vec![vec![1, 2, 3, 4], vec![5]], conflicts.graph.connected_components());
+    }
+
+    #[test]
+    fn test_conflicts_dot() {
+        let claims = parse_claims("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2").unwrap();
+        let conflicts = find_conflicts(&claims);
+        let dot = conflicts.graph.to_undirected_dot("conflicts", |a, b| Some(conflicts.area(*a, *b).to_string()));
+
+        assert_eq!("graph conflicts {\n    \"1\";\n    \"2\";\n    \"3\";\n    \"1\" -- \"2\" [label=\"4\"];\n}\n", dot);
+    }
+
+    #[test]
+    fn test_intersection() {
+        let a = Rectangle { left: 1, top: 3, width: 4, height: 4 };
+        let b = Rectangle { left: 3, top: 1, width: 4, height: 4 };
+        let shared = a.intersection(&b).unwrap();
+
+        assert_eq!((3, 3, 2, 2), (shared.left, shared.top, shared.width, shared.height));
+        assert!(a.intersection(&Rectangle { left: 5, top: 3, width: 1, height: 1 }).is_none());
+    }
+
+    #[test]
+    fn test_claim_index() {
+        let claims = parse_claims("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2").unwrap();
+        let index = ClaimIndex::new(&claims);
+        let ids = |found: Vec<&Claim>| found.iter().map(|c| c.id).collect::<Vec<i32>>();
+
+        assert_eq!(vec![1, 2], ids(index.at(4, 4)));
+        assert_eq!(vec![3], ids(index.at(6, 6)));
+        assert!(index.at(0, 0).is_empty());
+        assert_eq!(vec![1, 3], ids(index.intersecting(&Rectangle { left: 0, top: 6, width: 10, height: 3 })));
+        assert!(index.intersecting(&Rectangle { left: 4, top: 4, width: 0, height: 3 }).is_empty());
+        assert!(index.overlaps_other(0, &claims[0].rect));
+        assert!(!index.overlaps_other(2, &claims[2].rect));
+        assert!(index.overlaps_other(2, &claims[0].rect));
+    }
+
+    #[test]
+    fn test_claim_index_matches_scan() {
+        prop::check("claim index matches a scan", |rng| (random_claims(rng), ((rng.range(-5, 50) as i32, rng.range(-5, 50) as i32), (rng.range(0, 20) as i32, rng.range(0, 20) as i32))), |(claims, ((left, top), (width, height)))| {
+            let claims = to_claims(claims);
+            let query = Rectangle { left: *left, top: *top, width: *width, height: *height };
+            let expected = claims.iter().filter(|c| c.rect.overlaps(&query)).map(|c| c.id).collect::<Vec<i32>>();
+
+            ClaimIndex::new(&claims).intersecting(&query).iter().map(|c| c.id).collect::<Vec<i32>>() == expected
+        });
+    }
+
+    #[test]
+    fn test_standalone_claims() {
+        let claims = parse_claims(include_str!("input.txt")).unwrap();
+        let expected = claims.iter()
+            .filter(|a| claims.iter().all(|b| std::ptr::eq(*a, b) || !a.rect.overlaps(&b.rect)))
+            .map(|c| c.id)
+            .collect::<Vec<i32>>();
+
+        assert_eq!(1, expected.len());
+        assert_eq!(expected, standalone_claims(&claims).iter().map(|c| c.id).collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert!(matches!(parse_query("3, 4"), Ok(Query::Point(3, 4))));
+        match parse_query("1,2: 3x4") {
+            Ok(Query::Area(r)) => assert_eq!((1, 2, 3, 4), (r.left, r.top, r.width, r.height)),
+            _ => panic!("Expected an area"),
+        }
+        assert!(parse_query("3").is_err());
+        assert!(parse_query("3,x").is_err());
+        assert!(parse_query("1,2: 3").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Claim::parse("1 @ 1,2: 3x4").is_err());
+        assert!(Claim::parse("#1 1,2: 3x4").is_err());
+        assert!(Claim::parse("#1 @ 1,2: 3").is_err());
+        assert!(Claim::parse("#1 @ 1,2: 3xq").is_err());
+        assert!(parse_claims("#1 @ 1,2: 3x4\nnonsense").is_err());
+    }
+
+    #[test]
+    fn test_live_claims() {
+        let mut live = LiveClaims::new();
+        for claim in parse_claims("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2").unwrap() {
+            live.add(claim).unwrap();
+        }
+        assert_eq!(4, live.overlaps);
+        assert_eq!(vec![3], live.standalone());
+
+        live.add(Claim::parse("#4 @ 6,6: 3x3").unwrap()).unwrap();
+        assert_eq!(5, live.overlaps);
+        assert!(live.standalone().is_empty());
+
+        live.remove(2).unwrap();
+        assert_eq!(1, live.overlaps);
+        assert_eq!(vec![1], live.standalone());
+
+        assert!(live.remove(2).is_err());
+        assert!(live.add(Claim::parse("#1 @ 0,0: 1x1").unwrap()).is_err());
+        assert_eq!("Overlaps: 1, standalone: #1", live.summary());
+    }
+
+    #[test]
+    fn test_live_claims_grow_fabric() {
+        let mut live = LiveClaims::new();
+        live.add(Claim::parse("#1 @ 10,10: 2x2").unwrap()).unwrap();
+        live.add(Claim::parse("#2 @ 11,11: 2x2").unwrap()).unwrap();
+        live.add(Claim::parse("#3 @ 0,12: 12x1").unwrap()).unwrap();
+
+        assert_eq!((0, 10, 13, 3), (live.fabric.left, live.fabric.top, live.fabric.width, live.fabric.height));
+        assert_eq!(2, live.overlaps);
+
+        live.remove(1).unwrap();
+        live.remove(3).unwrap();
+        assert_eq!(0, live.overlaps);
+        assert_eq!(4, live.fabric.inches.iter().sum::<u32>());
+
+        // Growing rebuilds the fabric around the claims left, not every claim ever added.
+        live.add(Claim::parse("#4 @ 5000,5000: 1x1").unwrap()).unwrap();
+        live.remove(4).unwrap();
+        live.add(Claim::parse("#5 @ 0,0: 1x1").unwrap()).unwrap();
+        assert_eq!((0, 0, 13, 13), (live.fabric.left, live.fabric.top, live.fabric.width, live.fabric.height));
+        assert_eq!(5, live.fabric.inches.iter().sum::<u32>());
+    }
+
+    #[test]
+    fn test_live_claims_too_large() {
+        let mut live = LiveClaims::new();
+        live.add(Claim::parse("#1 @ 1000000,1000000: 0x5").unwrap()).unwrap();
+        live.add(Claim::parse("#2 @ 0,0: 2x2").unwrap()).unwrap();
+        assert_eq!((0, 0, 2, 2), (live.fabric.left, live.fabric.top, live.fabric.width, live.fabric.height));
+
+        assert!(live.add(Claim::parse("#3 @ 1000000,1000000: 2x2").unwrap()).is_err());
+        assert_eq!(vec![1, 2], live.standalone());
+        assert!(live.remove(3).is_err());
+
+        live.add(Claim::parse("#3 @ 1,1: 2x2").unwrap()).unwrap();
+        assert_eq!(1, live.overlaps);
+        assert_eq!(vec![1], live.standalone());
+    }
+
+    #[test]
+    fn test_live_claims_match_batch() {
+        prop::check("live claims match a batch count", |rng| (random_claims(rng), rng.range(0, 12) as usize), |(claims, removed)| {
+            let mut live = LiveClaims::new();
+            for claim in to_claims(claims) {
+                live.add(claim).unwrap();
+            }
+            // Claim IDs count up from 1, so this withdraws the first claims.
+            for id in 1..=(*removed).min(claims.len()) {
+                live.remove(id as i32).unwrap();
+            }
+
+            let kept = to_claims(claims).into_iter().skip(*removed).collect::<Vec<Claim>>();
+            let standalone = standalone_claims(&kept).iter().map(|c| c.id).collect::<Vec<i32>>();
+
+            live.overlaps as u64 == overlap_area(&kept) && live.standalone() == standalone
+        });
+    }
+
+    #[test]
+    fn test_statistics() {
+        let claims = parse_claims("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n#4 @ 4,4: 1x1").unwrap();
+        let stats = Fabric::filled(Fabric::around(&claims).unwrap(), &claims).statistics();
+
+        assert_eq!(vec![4, 28, 3, 1], stats.histogram);
+        assert_eq!(4, stats.unclaimed());
+        assert_eq!(4, stats.overlapping());
+        assert_eq!(3, stats.max_depth);
+        assert_eq!(Some((4, 4)), stats.deepest);
+        assert_eq!(
+            "{\"fabric\": {\"left\": 1, \"top\": 1, \"width\": 6, \"height\": 6}, \"unclaimed\": 4, \"overlapping\": 4, \"max_depth\": 3, \"max_depth_at\": {\"x\": 4, \"y\": 4}, \"histogram\": [4, 28, 3, 1], \"clipped\": []}",
+            stats.to_json(&[]));
+    }
+
+    #[test]
+    fn test_statistics_empty() {
+        let stats = Fabric::around(&[]).unwrap().statistics();
+
+        assert_eq!(vec![0], stats.histogram);
+        assert_eq!(None, stats.deepest);
+        assert!(stats.to_json(&[]).contains("\"max_depth_at\": null"));
+    }
+
+    #[test]
+    fn test_statistics_on_input() {
+        let claims = parse_claims(include_str!("input.txt")).unwrap();
+        let stats = Fabric::filled(Fabric::around(&claims).unwrap(), &claims).statistics();
+
+        assert_eq!(overlap_area(&claims), stats.overlapping() as u64);
+        assert_eq!((stats.width * stats.height) as i64, stats.histogram.iter().sum::<i64>());
+    }
+
+    #[test]
+    fn test_generated_input_parses() {
+        let claims = parse_claims(&generate_input(300, &mut Rng::new(5))).unwrap();
+
+        assert_eq!(300, claims.len());
+        assert!(claims.iter().all(|c| c.rect.left + c.rect.width <= FABRIC_SIZE as i32));
+        assert!(claims.iter().all(|c| c.rect.top + c.rect.height <= FABRIC_SIZE as i32));
+    }
+
+    #[test]
+    fn test_count_overlaps_matches_naive() {
+        prop::check("count_overlaps matches naive count", random_claims, |claims| {
+            let claims = to_claims(claims);
+            count_overlaps(&claims) == naive_count_overlaps(&claims)
+        });
+    }
+
+    /// A claim as `((left, top), (width, height))` so it can be shrunk.
+    type ClaimSpec = ((i32, i32), (i32, i32));
+
+    fn random_claims(rng: &mut Rng) -> Vec<ClaimSpec> {
+        (0..rng.below(12))
+            .map(|_| ((rng.range(0, 40) as i32, rng.range(0, 40) as i32), (rng.range(1, 12) as i32, rng.range(1, 12) as i32)))
+            .collect()
+    }
+
+    fn to_claims(claims: &[ClaimSpec]) -> Vec<Claim> {
+        claims.iter()
+            .enumerate()
+            .map(|(i, &((left, top), (width, height)))| Claim { id: i as i32 + 1, rect: Rectangle { left, top, width, height } })
+            .collect()
+    }
+
+    fn naive_count_overlaps(claims: &[Claim]) -> i32 {
+        let mut total = 0;
+
+        for y in 0..60 {
+            for x in 0..60 {
+                let covering = claims.iter()
+                    .filter(|c| c.rect.left <= x && x < c.rect.left + c.rect.width && c.rect.top <= y && y < c.rect.top + c.rect.height)
+                    .count();
+
+                if covering >= 2 {
+                    total += 1;
+                }
+            }
+        }
+
+        total
+    }
 }
@@ -1,4 +1,5 @@
 use crate::util;
+use crate::util::rng::Rng;
 
 pub fn run_part_1(args: &[String]) {
     util::run_part_n("1", args, react_polymer);
@@ -8,6 +9,12 @@ pub fn run_part_2(args: &[String]) {
     util::run_part_n("2", args, find_best_result);
 }
 
+/// A polymer of `size` units.
+pub fn generate_input(size: usize, rng: &mut Rng) -> String {
+    let units = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ".chars().collect::<Vec<char>>();
+    (0..size).map(|_| *rng.choose(&units)).collect()
+}
+
 fn react_polymer(filename: &String) -> util::AppResult {
     let polymer = util::read_file_input(filename)?;
     let result = perform_reaction(&polymer);
@@ -35,7 +42,7 @@ fn perform_reaction(polymer: &str) -> String {
 }
 
 fn same_char_different_case(c1: char, c2: char) -> bool {
-    c1 != c2 && c1.to_ascii_lowercase() == c2.to_ascii_lowercase()
+    c1 != c2 && c1.eq_ignore_ascii_case(&c2)
 }
 
 fn find_best_result(filename: &String) -> util::AppResult {
@@ -48,7 +55,7 @@ fn find_best_result(filename: &String) -> util::AppResult {
 }
 
 fn test_all_polymers(polymer: &str) -> usize {
-    let mut best = std::usize::MAX;
+    let mut best = usize::MAX;
 
     for to_remove in "abcdefghijklmnopqrstuvwxyz".chars() {
         let test = remove_unit_from(polymer, to_remove);
@@ -76,8 +83,10 @@ fn remove_unit_from(polymer: &str, removing_unit: char) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::util::prop;
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_same_char_different_case() {
         assert_eq!(true, same_char_different_case('a', 'A'));
         assert_eq!(false, same_char_different_case('A', 'A'));
@@ -94,8 +103,29 @@ mod tests {
         assert_eq!("dabCBAcaDA", perform_reaction("dabAcCaCBAcCcaDA"));
     }
 
+    #[test]
+    fn test_perform_reaction_is_idempotent() {
+        prop::check("perform_reaction is idempotent", random_polymer, |polymer| {
+            let reacted = perform_reaction(polymer);
+            perform_reaction(&reacted) == reacted
+        });
+    }
+
+    #[test]
+    fn test_generated_input_parses() {
+        let polymer = generate_input(1000, &mut Rng::new(9));
+
+        assert_eq!(1000, polymer.len());
+        assert!(perform_reaction(&polymer).len() <= 1000);
+    }
+
     #[test]
     fn test_find_best_result() {
         assert_eq!(4, test_all_polymers("dabAcCaCBAcCcaDA"));
     }
+
+    fn random_polymer(rng: &mut Rng) -> String {
+        let length = rng.below(40);
+        (0..length).map(|_| *rng.choose(&['a', 'A', 'b', 'B', 'c', 'C'])).collect()
+    }
 }
@@ -1,4 +1,6 @@
 use crate::util;
+use crate::util::graph::Graph;
+use crate::util::rng::Rng;
 
 pub fn run_part_1(args: &[String]) {
     util::run_part_n("1", args, determine_steps);
@@ -8,9 +10,48 @@ pub fn run_part_2(args: &[String]) {
     util::run_part_n("2", args, calculate_time);
 }
 
+pub fn run_dot(args: &[String]) {
+    match args {
+        [filename, from] => {
+            if let Err(e) = print_dot(filename, Some(from)) {
+                println!("Day 7 Failed: {}", e);
+            }
+        },
+        _ => util::run_part_n("7", args, |filename| print_dot(filename, None)),
+    };
+}
+
+/// Dependencies between up to 26 steps (`size` is capped at 26). Steps are
+/// shuffled and each one depends on at least one earlier step, so the result is
+/// always acyclic and every step appears.
+pub fn generate_input(size: usize, rng: &mut Rng) -> String {
+    let mut steps = "ABCDEFGHIJKLMNOPQRSTUVWXYZ".chars().take(size.max(2)).collect::<Vec<char>>();
+    for i in (1..steps.len()).rev() {
+        steps.swap(i, rng.below(i + 1));
+    }
+
+    let mut lines = Vec::new();
+    for after in 1..steps.len() {
+        let mut before = vec![rng.below(after)];
+        before.extend((0..rng.below(3)).map(|_| rng.below(after)));
+        before.sort();
+        before.dedup();
+
+        for b in before {
+            lines.push(format!("Step {} must be finished before step {} can begin.", steps[b], steps[after]));
+        }
+    }
+
+    for i in (1..lines.len()).rev() {
+        lines.swap(i, rng.below(i + 1));
+    }
+
+    lines.join("\n")
+}
+
 fn determine_steps(filename: &String) -> util::AppResult {
-    let processes = read_steps(filename)?;
-    let result = create_steps_list(&processes);
+    let steps = read_steps(filename)?;
+    let result = create_steps_list(&steps)?;
 
     println!("Steps: {}", result);
 
@@ -18,56 +59,76 @@ fn determine_steps(filename: &String) -> util::AppResult {
     Ok(())
 }
 
-fn create_steps_list(steps: &Vec<Process>) -> String {
-    let mut result = String::new();
-
-    let mut steps_done = Vec::new();
-    while steps.len() != steps_done.len() {
-        let doable = find_available_steps(steps, &steps_done);
-        let next_step = determine_next_step_alphabetically(&doable);
-        result.push(next_step);
-        steps_done.push(next_step);
+fn create_steps_list(steps: &Graph<char>) -> util::AppResult<String> {
+    match steps.topological_sort() {
+        Ok(order) => Ok(order.into_iter().collect()),
+        Err(cycle) => Err(util::AppError::AppError(format!("Steps contain a cycle: {}", cycle))),
     }
-
-    result
 }
 
 fn calculate_time(filename: &String) -> util::AppResult {
-    let processes = read_steps(filename)?;
+    let steps = read_steps(filename)?;
     let available_workers = 5;
     let base_action_time = 60;
 
-    let result = simulate_processes(&processes, available_workers, base_action_time);
+    if let Some(cycle) = steps.find_cycle() {
+        return Err(util::AppError::AppError(format!("Steps contain a cycle: {}", cycle)));
+    }
+
+    let result = simulate_processes(&steps, available_workers, base_action_time);
 
     println!("Time taken: {}", result);
 
     Ok(())
 }
 
-fn simulate_processes(steps: &Vec<Process>, workers: i32, base_action_time: i32) -> i32 {
+fn print_dot(filename: &String, from: Option<&String>) -> util::AppResult {
+    let mut steps = read_steps(filename)?;
+
+    if let Some(from) = from {
+        let step = from.chars().next().unwrap_or(' ');
+        if !steps.contains(&step) {
+            return Err(util::AppError::AppError(format!("Unknown step: {}", from)));
+        }
+
+        steps = steps.subgraph(&steps.reachable_from(&step));
+    }
+
+    print!("{}", steps.to_dot("steps"));
+
+    Ok(())
+}
+
+fn simulate_processes(steps: &Graph<char>, workers: i32, base_action_time: i32) -> i32 {
     let mut current_time = 0;
 
     let mut steps_done = Vec::new();
-    let mut available_actions = find_available_steps(steps, &steps_done);
     let mut workers: Vec<(Option<char>, i32)> = vec![(None, 0); workers as usize];
 
-    for i in 0..std::cmp::min(available_actions.len(), workers.len()) {
-        workers[i].0 = Some(available_actions[i]);
-        workers[i].1 = get_process(available_actions[i], steps).unwrap().duration(base_action_time);
-    }
+    loop {
+        let in_progress = workers.iter().filter_map(|w| w.0).collect::<Vec<char>>();
+        let mut available_actions = find_available_unstarted_steps(steps, &steps_done, &in_progress);
+        available_actions.sort();
+        let mut available_actions = available_actions.into_iter();
 
-    available_actions.clear();
+        for worker in workers.iter_mut().filter(|w| w.0.is_none()) {
+            match available_actions.next() {
+                Some(next) => *worker = (Some(next), duration(next, base_action_time)),
+                None => break,
+            }
+        }
+
+        let next_finish = workers.iter().filter(|w| w.0.is_some()).map(|w| w.1).min();
+        let elapsed = match next_finish {
+            Some(elapsed) => elapsed,
+            None => break,
+        };
 
-    while steps.len() != steps_done.len() {
-        // println!("current_time: {:?}", current_time);
-        // println!("steps_done: {:?}", steps_done);
-        // println!("available_actions: {:?}", available_actions);
-        // println!("workers: {:?}", workers);
-        // println!("");
+        current_time += elapsed;
 
         for worker in workers.iter_mut() {
             if let Some(c) = worker.0 {
-                worker.1 -= 1;
+                worker.1 -= elapsed;
 
                 if worker.1 <= 0 {
                     steps_done.push(c);
@@ -75,73 +136,30 @@ fn simulate_processes(steps: &Vec<Process>, workers: i32, base_action_time: i32)
                 }
             }
         }
-
-        let in_progress = &workers.iter().filter_map(|w| w.0).collect();
-        let doable = find_available_unstarted_steps(steps, &steps_done, in_progress);
-        for maybe_new in &doable {
-            if !available_actions.contains(maybe_new) {
-                available_actions.push(maybe_new.clone());
-            }
-        }
-
-        for worker in workers.iter_mut() {
-            if worker.0.is_none() && available_actions.len() > 0 {
-                let next = determine_next_step_alphabetically(&available_actions);
-                let process = get_process(next, steps).unwrap();
-                let index = available_actions.iter().position(|x| *x == next).unwrap();
-                available_actions.remove(index);
-                worker.0 = Some(next);
-                worker.1 = process.duration(base_action_time);
-            }
-        }
-
-        current_time += 1;
     }
 
     current_time
 }
 
-fn get_process(id: char, processes: &Vec<Process>) -> Option<&Process> {
-    processes.iter().filter(|p| p.id == id).next()
-}
-
-fn find_available_steps(steps: &Vec<Process>, done: &Vec<char>) -> Vec<char> {
-    find_available_unstarted_steps_internal(steps, done, None)
-}
-
-fn find_available_unstarted_steps(steps: &Vec<Process>, done: &Vec<char>, started: &Vec<char>) -> Vec<char> {
-    find_available_unstarted_steps_internal(steps, done, Some(started))
-}
-
-fn find_available_unstarted_steps_internal(steps: &Vec<Process>, done: &Vec<char>, started: Option<&Vec<char>>) -> Vec<char> {
-    steps.iter()
-        .filter(|p| !done.contains(&p.id) && !started.map(|s| s.contains(&p.id)).unwrap_or(true))
-        .filter(|p| dependencies_fulfilled(&p.dependencies, done))
-        .map(|p| p.id)
+fn find_available_unstarted_steps(steps: &Graph<char>, done: &[char], started: &[char]) -> Vec<char> {
+    steps.nodes()
+        .filter(|p| !done.contains(p) && !started.contains(p))
+        .filter(|p| steps.predecessors(p).iter().all(|d| done.contains(d)))
+        .cloned()
         .collect::<Vec<char>>()
 }
 
-fn dependencies_fulfilled(deps: &Vec<char>, done: &Vec<char>) -> bool {
-    deps.iter().filter(|d| !done.contains(d)).any(|_| true)
+fn duration(step: char, base_action_time: i32) -> i32 {
+    base_action_time + (step as i32 - 'A' as i32) + 1
 }
 
-fn determine_next_step_alphabetically(options: &Vec<char>) -> char {
-    let mut result = 'Z';
-
-    for c in options.iter() {
-        result = std::cmp::min(*c, result);
-    }
-
-    result
-}
-
-fn read_steps(filename: &String) -> Result<Vec<Process>, util::AppError> {
+fn read_steps(filename: &String) -> Result<Graph<char>, util::AppError> {
     let input = util::read_file_input(filename)?;
-    Ok(parse_input(&input))
+    parse_input(&input)
 }
 
-fn parse_input(s: &str) -> Vec<Process> {
-    let mut map = std::collections::HashMap::new();
+fn parse_input(s: &str) -> util::AppResult<Graph<char>> {
+    let mut graph = Graph::new();
 
     for line in s.lines() {
         let d = line.split(' ').collect::<Vec<&str>>();
@@ -150,43 +168,13 @@ fn parse_input(s: &str) -> Vec<Process> {
                 let key = y.chars().next().unwrap();
                 let dep = x.chars().next().unwrap();
 
-                map.entry(key).or_insert_with(|| Process::new(key)).add_dependency(dep);
-                map.entry(dep).or_insert_with(|| Process::new(dep));
+                graph.add_edge(dep, key);
             },
-            _ => panic!("Invalid input!")
+            _ => return Err(util::AppError::AppError(format!("Invalid input: {}", line)))
         };
     }
 
-    let mut results = Vec::new();
-
-    for value in map.values() {
-        results.push(value.clone());
-    }
-
-    results
-}
-
-#[derive(Clone, Debug)]
-struct Process {
-    id: char,
-    dependencies: Vec<char>,
-}
-
-impl Process {
-    fn new(id: char) -> Process {
-        Process{
-            id,
-            dependencies: Vec::new(),
-        }
-    }
-
-    fn add_dependency(&mut self, new_dep: char) {
-        self.dependencies.push(new_dep);
-    }
-
-    fn duration(&self, base_action_time: i32) -> i32 {
-        base_action_time + (self.id as i32 - 'A' as i32) + 1
-    }
+    Ok(graph)
 }
 
 #[cfg(test)]
@@ -197,25 +185,22 @@ mod tests {
     fn test_parse_dependency_input() {
         let input = get_input();
 
-        println!("{:?}", input);
-
         assert_eq!(6, input.len());
 
-        let c = get_process(&input, 'C').unwrap();
-        assert_eq!(0, c.dependencies.len());
+        assert_eq!(0, input.predecessors(&'C').len());
 
-        let e = get_process(&input, 'E').unwrap();
-        assert_eq!(3, e.dependencies.len());
-        assert!(e.dependencies.contains(&'B'));
-        assert!(e.dependencies.contains(&'D'));
-        assert!(e.dependencies.contains(&'F'));
+        let e = input.predecessors(&'E');
+        assert_eq!(3, e.len());
+        assert!(e.contains(&&'B'));
+        assert!(e.contains(&&'D'));
+        assert!(e.contains(&&'F'));
     }
 
     #[test]
     fn test_create_steps_list() {
         let input = get_input();
         let result = create_steps_list(&input);
-        assert_eq!("CABDFE", result);
+        assert_eq!("CABDFE", result.unwrap());
     }
 
     #[test]
@@ -227,17 +212,23 @@ mod tests {
         assert_eq!(15, simulate_processes(&input, workers, time));
     }
 
-    fn get_process(processes: &Vec<Process>, id: char) -> Option<&Process> {
-        for p in processes.iter() {
-            if p.id == id {
-                return Some(p);
-            }
-        }
+    #[test]
+    fn test_generated_input_parses() {
+        let input = parse_input(&generate_input(26, &mut Rng::new(4))).unwrap();
+
+        assert_eq!(26, input.len());
+        assert_eq!(26, create_steps_list(&input).unwrap().len());
+    }
+
+    #[test]
+    fn test_cycle_is_reported() {
+        let input = parse_input("Step A must be finished before step B can begin.
+Step B must be finished before step A can begin.").unwrap();
 
-        None
+        assert!(create_steps_list(&input).is_err());
     }
 
-    fn get_input() -> Vec<Process> {
+    fn get_input() -> Graph<char> {
         let s: &str = "Step C must be finished before step A can begin.
 Step C must be finished before step F can begin.
 Step A must be finished before step B can begin.
@@ -245,6 +236,6 @@ Step A must be finished before step D can begin.
 Step B must be finished before step E can begin.
 Step D must be finished before step E can begin.
 Step F must be finished before step E can begin.";
-        parse_input(s)
+        parse_input(s).unwrap()
     }
 }
@@ -1,4 +1,5 @@
 use crate::util::*;
+use crate::util::rng::Rng;
 
 pub fn run_part_1(args: &[String]) {
     run_part_n("1", args, sum_metadata_entries);
@@ -8,6 +9,37 @@ pub fn run_part_2(args: &[String]) {
     run_part_n("2", args, calculate_checksum);
 }
 
+const GENERATED_MAX_DEPTH: usize = 1000;
+
+/// A tree of `size` nodes. Each node hangs off one of the most recently added
+/// nodes, which gives long chains, but depth is capped so the recursive parser copes.
+pub fn generate_input(size: usize, rng: &mut Rng) -> String {
+    let mut tree = Tree::new();
+    let mut depths = Vec::new();
+
+    for id in 0..size.max(1) {
+        let mut node = Node::new();
+        node.metadata = (0..rng.range(1, 3)).map(|_| rng.range(1, 9) as usize).collect();
+        tree.add_node(node);
+
+        if id == 0 {
+            depths.push(0);
+            continue;
+        }
+
+        let mut parent = id - 1 - rng.below(id.min(8));
+        if depths[parent] >= GENERATED_MAX_DEPTH {
+            parent = 0;
+        }
+
+        depths.push(depths[parent] + 1);
+        tree.nodes[parent].children.push(id);
+    }
+
+    tree.root_node = Some(0);
+    tree.serialize()
+}
+
 fn sum_metadata_entries(filename: &String) -> AppResult {
     let tree = read_tree(filename)?;
     let result = tree.sum_metadata();
@@ -28,7 +60,7 @@ fn calculate_checksum(filename: &String) -> AppResult {
 
 fn read_tree(filename: &String) -> Result<Tree, AppError> {
     let input = read_file_input(filename)?;
-    Ok(parse_to_tree(&input)?)
+    parse_to_tree(&input)
 }
 
 fn parse_to_tree(input: &str) -> Result<Tree, AppError> {
@@ -63,7 +95,7 @@ fn read_usize_from<T>(iter: &mut T) -> Result<usize, AppError>
     match iter.next() {
         Some(Ok(result)) => Ok(result),
         None => Err(AppError::AppError(String::from("Unexpected EOF when parsing file"))),
-        Some(Err(err)) => Err(AppError::AppError(String::from(format!("Unable to convert string to number. {}", err)))),
+        Some(Err(err)) => Err(AppError::AppError(format!("Unable to convert string to number. {}", err))),
     }
 }
 
@@ -99,7 +131,7 @@ impl Tree {
 
     fn node_checksum(&self, node_id: usize) -> usize {
         let node = self.nodes.get(node_id).unwrap();
-        if node.children.len() == 0 {
+        if node.children.is_empty() {
             node.metadata.iter().sum::<usize>()
         } else {
             let mut result = 0;
@@ -113,6 +145,27 @@ impl Tree {
             result
         }
     }
+
+    /// Writes the tree back out in the puzzle's input format.
+    fn serialize(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(root) = self.root_node {
+            self.serialize_node(root, &mut parts);
+        }
+        parts.join(" ")
+    }
+
+    fn serialize_node(&self, node_id: usize, parts: &mut Vec<String>) {
+        let node = &self.nodes[node_id];
+        parts.push(node.children.len().to_string());
+        parts.push(node.metadata.len().to_string());
+
+        for child in node.children.iter() {
+            self.serialize_node(*child, parts);
+        }
+
+        parts.extend(node.metadata.iter().map(|m| m.to_string()));
+    }
 }
 
 struct Node {
@@ -132,6 +185,7 @@ impl Node {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::util::prop::{self, Shrink};
 
     #[test]
     fn test_sum_metadata() {
@@ -145,8 +199,72 @@ mod tests {
         assert_eq!(66, tree.checksum());
     }
 
+    #[test]
+    fn test_generated_input_parses() {
+        let input = generate_input(5000, &mut Rng::new(2));
+        let tree = parse_to_tree(&input).unwrap();
+
+        assert_eq!(5000, tree.nodes.len());
+        assert_eq!(input, tree.serialize());
+    }
+
+    #[test]
+    fn test_serialize_round_trips() {
+        assert_eq!("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2", get_tree().serialize());
+
+        prop::check("parse_to_tree round-trips", random_tree, |spec| {
+            let input = spec.to_input();
+            parse_to_tree(&input).map(|tree| tree.serialize() == input).unwrap_or(false)
+        });
+    }
+
+    /// A tree description that can be generated and shrunk independently of `Tree`.
+    #[derive(Clone, Debug)]
+    struct TreeSpec {
+        children: Vec<TreeSpec>,
+        metadata: Vec<usize>,
+    }
+
+    impl TreeSpec {
+        fn to_input(&self) -> String {
+            let mut parts = vec![self.children.len().to_string(), self.metadata.len().to_string()];
+            parts.extend(self.children.iter().map(|c| c.to_input()));
+            parts.extend(self.metadata.iter().map(|m| m.to_string()));
+            parts.join(" ")
+        }
+    }
+
+    impl Shrink for TreeSpec {
+        fn shrink(&self) -> Vec<TreeSpec> {
+            let mut result = self.children.clone();
+
+            for children in self.children.shrink() {
+                result.push(TreeSpec { children, metadata: self.metadata.clone() });
+            }
+
+            for metadata in self.metadata.shrink() {
+                result.push(TreeSpec { children: self.children.clone(), metadata });
+            }
+
+            result
+        }
+    }
+
+    fn random_tree(rng: &mut Rng) -> TreeSpec {
+        random_subtree(rng, 4)
+    }
+
+    fn random_subtree(rng: &mut Rng, depth: usize) -> TreeSpec {
+        let child_count = if depth == 0 { 0 } else { rng.below(4) };
+
+        TreeSpec {
+            children: (0..child_count).map(|_| random_subtree(rng, depth - 1)).collect(),
+            metadata: (0..rng.below(4)).map(|_| rng.below(100)).collect(),
+        }
+    }
+
     fn get_tree() -> Tree {
         let input = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
-        parse_to_tree(&input).unwrap()
+        parse_to_tree(input).unwrap()
     }
 }
@@ -1,8 +1,6 @@
-use std::num::{NonZeroU32};
-
 use crate::util::*;
-
-use linked_list::{LinkedList, Cursor};
+use crate::util::circular::CircularList;
+use crate::util::rng::Rng;
 
 pub fn run_part_1(args: &[String]) {
     run_part_n("1", args, calculate_winner);
@@ -12,6 +10,11 @@ pub fn run_part_2(args: &[String]) {
     run_part_n("2", args, calculate_bigger_winner);
 }
 
+/// A game whose last marble is worth `size` points.
+pub fn generate_input(size: usize, rng: &mut Rng) -> String {
+    format!("{} players; last marble is worth {} points", rng.range(2, 500), size)
+}
+
 fn calculate_winner(filename: &String) -> AppResult {
     let (player_count, max_marble_score) = read_input(filename)?;
     let results = simulate_game(player_count, max_marble_score);
@@ -32,22 +35,19 @@ fn calculate_bigger_winner(filename: &String) -> AppResult {
 
 fn simulate_game(player_count: usize, max_marble_score: usize) -> Vec<usize> {
     let mut player_score = vec!(0; player_count);
-    let mut marbles = LinkedList::new();
-    marbles.push_back(0);
-    let mut cursor = marbles.cursor();
+    let mut marbles = CircularList::with_capacity(max_marble_score + 1);
+    marbles.insert(0);
 
     let mut current_player = 0;
  
     for marble_score in 1..=max_marble_score {
         if marble_score % 23 == 0 {
-            let remove_value = counter_clockwise_step_n(&mut cursor, NonZeroU32::new(7).unwrap());
-            *player_score.get_mut(current_player).unwrap() += *remove_value;
-            *player_score.get_mut(current_player).unwrap() += marble_score;
-            cursor.remove();
+            marbles.rotate_left(7);
+            let remove_value = marbles.remove().unwrap();
+            player_score[current_player] += remove_value + marble_score;
         } else {
-            clockwise_step_n(&mut cursor, NonZeroU32::new(2).unwrap());
-            cursor.insert(marble_score);
-
+            marbles.rotate_right(1);
+            marbles.insert(marble_score);
         }
 
         current_player = (current_player + 1) % player_count;
@@ -56,41 +56,12 @@ fn simulate_game(player_count: usize, max_marble_score: usize) -> Vec<usize> {
     player_score
 }
 
-fn clockwise_step_n<'a, T: 'a>(c: &'a mut Cursor<T>, num: NonZeroU32) -> &'a mut T {
-    for _ in 0..num.get() - 1 {
-        clockwise_step(c);
-    }
-
-    clockwise_step(c)
-}
-
-fn clockwise_step<'a, T: 'a>(c: &'a mut Cursor<T>) -> &'a mut T {
-    c.next();
-    if c.peek_next().is_none() {
-        c.next();
-    }
-
-    c.peek_next().unwrap()
-}
-
-fn counter_clockwise_step_n<'a, T: 'a>(c: &'a mut Cursor<T>, num: NonZeroU32) -> &'a mut T {
-    for _ in 0..num.get() - 1 {
-        counter_clockwise_step(c);
-    }
-
-    counter_clockwise_step(c)
-}
-
-fn counter_clockwise_step<'a, T: 'a>(c: &'a mut Cursor<T>) -> &'a mut T {
-    if c.prev().is_none() {
-        c.prev();
-    }
-
-    c.peek_next().unwrap()
-}
-
 fn read_input(filename: &String) -> AppResult<(usize, usize)> {
     let input = read_file_input(filename)?;
+    parse_input(&input)
+}
+
+fn parse_input(input: &str) -> AppResult<(usize, usize)> {
     let split = input.split(" ").collect::<Vec<&str>>();
 
     match split.as_slice() {
@@ -115,4 +86,12 @@ mod tests {
         assert_eq!(54718, *simulate_game(21, 6111).iter().max().unwrap());
         assert_eq!(37305, *simulate_game(30, 5807).iter().max().unwrap());
     }
+
+    #[test]
+    fn test_generated_input_parses() {
+        let (players, max_marble_score) = parse_input(&generate_input(7000, &mut Rng::new(8))).unwrap();
+
+        assert!((2..=500).contains(&players));
+        assert_eq!(7000, max_marble_score);
+    }
 }
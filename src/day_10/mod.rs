@@ -1,25 +1,42 @@
 use crate::util::*;
+use crate::util::image::{self, Image};
+use crate::util::rng::Rng;
 
 pub fn run_part_1(args: &[String]) {
-    run_part_n("1", args, calculate_word);
+    run_part_n_with_options("1", args, calculate_word);
 }
 
 pub fn run_part_2(args: &[String]) {
     run_part_n("2", args, |_| Ok(()));
 }
 
-fn calculate_word(filename: &String) -> AppResult {
-    let mut input = read_input(filename)?;
-    let output = find_most_likely_output(&mut input);
+/// `size` points that line up inside a 60x10 box after a few thousand seconds.
+pub fn generate_input(size: usize, rng: &mut Rng) -> String {
+    let time = rng.range(5_000, 15_000);
+
+    (0..size)
+        .map(|_| {
+            let (x, y) = (rng.range(0, 59), rng.range(0, 9));
+            let vx = rng.range(-5, 5);
+            let vy = *rng.choose(&[-5, -4, -3, -2, -1, 1, 2, 3, 4, 5]);
+            format!("position=<{:>6}, {:>6}> velocity=<{:>2}, {:>2}>", x - vx * time, y - vy * time, vx, vy)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn calculate_word(filename: &String, options: &Options) -> AppResult {
+    let input = read_input(filename)?;
+    let output = find_most_likely_output(&input);
 
     println!("best output:\n");
     display_output(&output.0, output.1);
 
-    Ok(())
+    image::save_from_options(options, |_| output_image(&output.0))
 }
 
-fn find_most_likely_output(input: &Vec<Spotlight>) -> (Vec<Spotlight>, i32) {
-    let mut current = input.clone();
+fn find_most_likely_output(input: &[Spotlight]) -> (Vec<Spotlight>, i32) {
+    let mut current = input.to_vec();
     let mut bounds = spotlight_bounding_rect(&current);
     let mut timer = 0;
 
@@ -36,8 +53,8 @@ fn find_most_likely_output(input: &Vec<Spotlight>) -> (Vec<Spotlight>, i32) {
     }
 }
 
-fn update(input: &Vec<Spotlight>) -> Vec<Spotlight> {
-    let mut new = input.clone();
+fn update(input: &[Spotlight]) -> Vec<Spotlight> {
+    let mut new = input.to_vec();
 
     for s in new.iter_mut() {
         s.update();
@@ -46,7 +63,7 @@ fn update(input: &Vec<Spotlight>) -> Vec<Spotlight> {
     new
 }
 
-fn display_output(spotlights: &Vec<Spotlight>, time: i32) {
+fn display_output(spotlights: &[Spotlight], time: i32) {
     let rect = spotlight_bounding_rect(spotlights);
     let mut strs = vec!(vec!(' '; rect.w as usize + 1); rect.h as usize + 1);
 
@@ -55,7 +72,7 @@ fn display_output(spotlights: &Vec<Spotlight>, time: i32) {
     }
 
     let strs = strs.iter()
-        .map(|x| x.into_iter().collect::<String>())
+        .map(|x| x.iter().collect::<String>())
         .collect::<Vec<String>>();
 
     for s in &strs {
@@ -65,15 +82,24 @@ fn display_output(spotlights: &Vec<Spotlight>, time: i32) {
     println!("Would have taken {} seconds", time);
 }
 
-fn spotlight_bounding_rect(spotlights: &Vec<Spotlight>) -> Rect {
+fn output_image(spotlights: &[Spotlight]) -> Image {
+    let rect = spotlight_bounding_rect(spotlights);
+    let lit = spotlights.iter()
+        .map(|s| ((s.position.x - rect.x) as usize, (s.position.y - rect.y) as usize))
+        .collect::<std::collections::HashSet<(usize, usize)>>();
+
+    Image::bitmap(rect.w as usize + 1, rect.h as usize + 1, |x, y| lit.contains(&(x, y)))
+}
+
+fn spotlight_bounding_rect(spotlights: &[Spotlight]) -> Rect {
     bounding_rect(spotlights.iter().map(|s| &s.position))
 }
 
 fn bounding_rect<'a>(points: impl Iterator<Item=&'a Vec2>) -> Rect {
-    let mut min_x = std::i32::MAX;
-    let mut min_y = std::i32::MAX;
-    let mut max_x = std::i32::MIN;
-    let mut max_y = std::i32::MIN;
+    let mut min_x = i32::MAX;
+    let mut min_y = i32::MAX;
+    let mut max_x = i32::MIN;
+    let mut max_y = i32::MIN;
 
     for p in points {
         min_x = std::cmp::min(min_x, p.x);
@@ -90,10 +116,11 @@ fn read_input(filename: &String) -> AppResult<Vec<Spotlight>> {
     parse_input(&input)
 }
 
+#[allow(clippy::ptr_arg)]
 fn parse_input(input: &String) -> AppResult<Vec<Spotlight>> {
     let mut results = Vec::new();
     for line in input.lines() {
-        results.push(Spotlight::parse(&line)?);
+        results.push(Spotlight::parse(line)?);
     }
     Ok(results)
 }
@@ -172,12 +199,17 @@ impl Rect {
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_input_parses() {
+        let input = parse_input(&generate_input(300, &mut Rng::new(6))).unwrap();
+        let (output, _) = find_most_likely_output(&input);
+        let bounds = spotlight_bounding_rect(&output);
 
-//     #[test]
-//     fn test_example() {
-//         unimplemented!();
-//     }
-// }
+        assert_eq!(300, input.len());
+        assert!(bounds.w < 60 && bounds.h < 10);
+    }
+}
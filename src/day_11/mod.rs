@@ -1,38 +1,45 @@
 use crate::util::*;
+use crate::util::image::{self, Format, Image};
+use crate::util::rng::Rng;
 
 const GRID_SIZE: i32 = 300;
 
 pub fn run_part_1(args: &[String]) {
-    run_part_n("1", args, get_best_fuel_cell_position);
+    run_part_n_with_options("1", args, get_best_fuel_cell_position);
 }
 
 pub fn run_part_2(args: &[String]) {
-    run_part_n("2", args, get_best_any_sized_fuel_cell_position);
+    run_part_n_with_options("2", args, get_best_any_sized_fuel_cell_position);
 }
 
-fn get_best_fuel_cell_position(filename: &String) -> AppResult {
+/// A grid serial number. The grid size is fixed, so `size` is unused.
+pub fn generate_input(_size: usize, rng: &mut Rng) -> String {
+    rng.range(1, 9999).to_string()
+}
+
+fn get_best_fuel_cell_position(filename: &String, options: &Options) -> AppResult {
     let input = read_file(filename)?;
     let output = find_best_fuel_cells(input);
 
     println!("Best fuel cell position: {},{}", output.0, output.1);
 
-    Ok(())
+    image::save_from_options(options, |format| grid_image(&generate_grid(input), format))
 }
 
-fn get_best_any_sized_fuel_cell_position(filename: &String) -> AppResult {
+fn get_best_any_sized_fuel_cell_position(filename: &String, options: &Options) -> AppResult {
     let input = read_file(filename)?;
     let output = find_best_any_sized_fuel_cells(input);
 
     println!("Best fuel cell position: {},{},{}", output.0, output.1, output.2);
 
-    Ok(())
+    image::save_from_options(options, |format| grid_image(&generate_grid(input), format))
 }
 
 fn find_best_fuel_cells(serial: i32) -> (i32, i32) {
     let grid = generate_grid(serial);
     
     let mut result = (0, 0);
-    let mut result_power = std::i32::MIN;
+    let mut result_power = i32::MIN;
 
     for y in 0..GRID_SIZE - 2 {
         for x in 0..GRID_SIZE - 2 {
@@ -51,7 +58,7 @@ fn find_best_any_sized_fuel_cells(serial: i32) -> (i32, i32, i32) {
     let grid = generate_grid(serial);
     
     let mut result = (0, 0, 0);
-    let mut result_power = std::i32::MIN;
+    let mut result_power = i32::MIN;
 
     for size in 1..GRID_SIZE {
         println!("{}", size);
@@ -81,6 +88,18 @@ fn generate_grid(serial: i32) -> Vec<i32> {
     result
 }
 
+/// Power levels run from -5 to 4: as a heat map in colour, or from black to white
+/// when saved as a `.pgm`.
+fn grid_image(grid: &[i32], format: Format) -> Image {
+    let size = GRID_SIZE as usize;
+    let power = |x: usize, y: usize| grid[y * size + x];
+
+    match format {
+        Format::Pgm => Image::graymap(size, size, |x, y| ((power(x, y) + 5) * 255 / 9) as u8),
+        _ => Image::pixmap(size, size, |x, y| image::heat_colour(power(x, y) as i64, -5, 4)),
+    }
+}
+
 fn grid_3x3_power(x: i32, y: i32, grid: &Vec<i32>) -> i32 {
     grid_nxn_power(x, y, grid, 3)
 }
@@ -101,6 +120,10 @@ fn cell_power_level(x: i32, y: i32, serial: i32) -> i32 {
 
 fn read_file(filename: &String) -> AppResult<i32> {
     let input = read_file_input(filename)?;
+    parse_input(&input)
+}
+
+fn parse_input(input: &str) -> AppResult<i32> {
     Ok(input.parse::<i32>()?)
 }
 
@@ -117,7 +140,7 @@ impl<'a> GridIter<'a> {
             grid: vec,
             start_pos: start,
             current_iter: 0,
-            iter_size: iter_size,
+            iter_size,
         }
     }
 }
@@ -165,4 +188,22 @@ mod tests {
         assert_eq!((33, 45), find_best_fuel_cells(18));
         assert_eq!((21, 61), find_best_fuel_cells(42));
     }
+
+    #[test]
+    fn test_grid_image_grayscale() {
+        let mut written = Vec::new();
+        grid_image(&generate_grid(8), Format::Pgm).write(&mut written, Format::Pgm).unwrap();
+        let header = b"P5\n300 300\n255\n";
+
+        assert_eq!(header.to_vec(), written[..header.len()].to_vec());
+        // The cell at 3,5 has the highest power level, 4.
+        assert_eq!(255, written[header.len() + 5 * 300 + 3]);
+    }
+
+    #[test]
+    fn test_generated_input_parses() {
+        let serial = parse_input(&generate_input(0, &mut Rng::new(10))).unwrap();
+
+        assert!((1..=9999).contains(&serial));
+    }
 }
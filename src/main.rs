@@ -19,11 +19,15 @@ fn main() {
     let day = args.get(1);
 
     match day {
+        Some(command) if command == "generate" => {
+            generate_input(&args[2..]);
+        },
         Some(day) => {
             run_day(day, &args[2..]);
         },
         _ => {
-            println!("Usage: <day number> ...[args]")
+            println!("Usage: <day number> ...[args]");
+            println!("       generate <day> [size] [seed]");
         }
     }
 }
@@ -32,10 +36,17 @@ fn run_day(day: &String, args: &[String]) {
     match day.as_ref() {
         "1.1" => day_01::run_part_1(args),
         "1.2" => day_01::run_part_2(args),
+        "1.stats" => day_01::run_stats(args),
+        "1.stream" => day_01::run_stream(args),
         "2.1" => day_02::run_part_1(args),
         "2.2" => day_02::run_part_2(args),
+        "2.query" => day_02::run_query(args),
         "3.1" => day_03::run_part_1(args),
         "3.2" => day_03::run_part_2(args),
+        "3.conflicts" => day_03::run_conflicts(args),
+        "3.query" => day_03::run_query(args),
+        "3.live" => day_03::run_live(args),
+        "3.stats" => day_03::run_stats(args),
         "4.1" => day_04::run_part_1(args),
         // "4.2" => day_04::run_part_2(args),
         "5.1" => day_05::run_part_1(args),
@@ -44,6 +55,7 @@ fn run_day(day: &String, args: &[String]) {
         // "6.2" => day_06::run_part_2(args),
         "7.1" => day_07::run_part_1(args),
         "7.2" => day_07::run_part_2(args),
+        "7.dot" => day_07::run_dot(args),
         "8.1" => day_08::run_part_1(args),
         "8.2" => day_08::run_part_2(args),
         "9.1" => day_09::run_part_1(args),
@@ -55,3 +67,44 @@ fn run_day(day: &String, args: &[String]) {
         _ => println!("Day not found: {}", day)
     };
 }
+
+fn generate_input(args: &[String]) {
+    let (day, size, seed) = match args {
+        [day] => (day, Ok(1000), Ok(0)),
+        [day, size] => (day, size.parse::<usize>(), Ok(0)),
+        [day, size, seed] => (day, size.parse::<usize>(), seed.parse::<u64>()),
+        _ => {
+            println!("Usage: generate <day> [size] [seed]");
+            return;
+        }
+    };
+
+    let (size, seed) = match (size, seed) {
+        (Ok(size), Ok(seed)) => (size, seed),
+        _ => {
+            println!("Size and seed must be non-negative numbers");
+            return;
+        }
+    };
+
+    let mut rng = util::rng::Rng::new(seed);
+
+    let input = match day.as_ref() {
+        "1" => day_01::generate_input(size, &mut rng),
+        "2" => day_02::generate_input(size, &mut rng),
+        "3" => day_03::generate_input(size, &mut rng),
+        "5" => day_05::generate_input(size, &mut rng),
+        "7" => day_07::generate_input(size, &mut rng),
+        "8" => day_08::generate_input(size, &mut rng),
+        "9" => day_09::generate_input(size, &mut rng),
+        "10" => day_10::generate_input(size, &mut rng),
+        "11" => day_11::generate_input(size, &mut rng),
+        _ => {
+            println!("No generator for day: {}", day);
+            return;
+        }
+    };
+
+    // Some parsers split on spaces rather than lines, so no trailing newline is written.
+    print!("{}", input);
+}
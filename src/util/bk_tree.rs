@@ -0,0 +1,136 @@
+/// A BK-tree: a metric tree for finding the stored values closest to a query, for
+/// any distance that satisfies the triangle inequality, such as edit distance.
+/// Each child is filed under its distance from its parent, so a search only has to
+/// descend into children whose distance is within the search radius of the query's.
+pub struct BkTree<T, D> {
+    nodes: Vec<BkNode<T>>,
+    distance: D,
+}
+
+struct BkNode<T> {
+    value: T,
+    /// Pairs of distance from this node and child node index.
+    children: Vec<(usize, usize)>,
+}
+
+impl<T, D: Fn(&T, &T) -> usize> BkTree<T, D> {
+    pub fn new(distance: D) -> Self {
+        BkTree { nodes: Vec::new(), distance }
+    }
+
+    /// Adds `value`, returning `false` if an equal value (distance 0) is already stored.
+    pub fn insert(&mut self, value: T) -> bool {
+        let index = self.nodes.len();
+        let mut node = 0;
+
+        if !self.nodes.is_empty() {
+            loop {
+                let d = (self.distance)(&value, &self.nodes[node].value);
+                if d == 0 {
+                    return false;
+                }
+
+                match self.nodes[node].children.iter().find(|&&(edge, _)| edge == d) {
+                    Some(&(_, child)) => node = child,
+                    None => {
+                        self.nodes[node].children.push((d, index));
+                        break;
+                    },
+                }
+            }
+        }
+
+        self.nodes.push(BkNode { value, children: Vec::new() });
+        true
+    }
+
+    /// The stored value closest to `query` and its distance, if any is within
+    /// `max_distance`. Ties go to the value inserted first.
+    pub fn nearest(&self, query: &T, max_distance: usize) -> Option<(&T, usize)> {
+        let mut best: Option<(usize, usize)> = None;
+        let mut stack = if self.nodes.is_empty() { Vec::new() } else { vec![0] };
+
+        while let Some(node) = stack.pop() {
+            let d = (self.distance)(query, &self.nodes[node].value);
+            let better = match best {
+                Some((best_distance, best_node)) => d < best_distance || (d == best_distance && node < best_node),
+                None => d <= max_distance,
+            };
+            if better {
+                best = Some((d, node));
+            }
+
+            let radius = best.map_or(max_distance, |(best_distance, _)| best_distance);
+            for &(edge, child) in self.nodes[node].children.iter() {
+                if edge.abs_diff(d) <= radius {
+                    stack.push(child);
+                }
+            }
+        }
+
+        best.map(|(d, node)| (&self.nodes[node].value, d))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(values: &[i64]) -> BkTree<i64, impl Fn(&i64, &i64) -> usize> {
+        let mut tree = BkTree::new(|a: &i64, b: &i64| a.abs_diff(*b) as usize);
+        for &value in values.iter() {
+            tree.insert(value);
+        }
+        tree
+    }
+
+    #[test]
+    fn test_nearest() {
+        let tree = tree(&[10, 3, 25, 17, 40]);
+
+        assert_eq!(Some((&17, 1)), tree.nearest(&18, 5));
+        assert_eq!(Some((&40, 0)), tree.nearest(&40, 0));
+        assert_eq!(None, tree.nearest(&32, 6));
+        assert_eq!(Some((&3, 103)), tree.nearest(&-100, usize::MAX));
+    }
+
+    #[test]
+    fn test_ties_go_to_first_inserted() {
+        assert_eq!(Some((&25, 5)), tree(&[10, 25, 15]).nearest(&20, 10));
+        assert_eq!(Some((&15, 5)), tree(&[10, 15, 25]).nearest(&20, 10));
+    }
+
+    #[test]
+    fn test_duplicates_and_empty() {
+        let mut empty = tree(&[]);
+        assert_eq!(None, empty.nearest(&1, usize::MAX));
+
+        assert!(empty.insert(4));
+        assert!(!empty.insert(4));
+        assert_eq!(Some((&4, 0)), empty.nearest(&4, 0));
+    }
+
+    #[test]
+    fn test_matches_linear_scan() {
+        use crate::util::prop;
+
+        prop::check("bk tree nearest matches a linear scan", |rng| {
+            let values = (0..rng.range(0, 40)).map(|_| rng.range(-100, 100)).collect::<Vec<i64>>();
+            (values, (rng.range(-120, 120), rng.range(0, 30)))
+        }, |(values, (query, max_distance))| {
+            let mut unique: Vec<i64> = Vec::new();
+            for value in values.iter() {
+                if !unique.contains(value) {
+                    unique.push(*value);
+                }
+            }
+
+            let expected = unique.iter()
+                .map(|v| (v, v.abs_diff(*query) as usize))
+                .filter(|&(_, d)| d <= *max_distance as usize)
+                .min_by_key(|&(_, d)| d);
+
+            tree(values).nearest(query, *max_distance as usize) == expected
+        });
+    }
+}
@@ -0,0 +1,180 @@
+/// A circular doubly linked list stored in a `Vec`, with links held as indices.
+/// There is always a current position (the cursor) unless the list is empty.
+/// Rotating right moves the cursor clockwise, rotating left moves it counter-clockwise.
+pub struct CircularList<T> {
+    nodes: Vec<Node<T>>,
+    free: Vec<usize>,
+    cursor: Option<usize>,
+    len: usize,
+}
+
+struct Node<T> {
+    value: Option<T>,
+    prev: usize,
+    next: usize,
+}
+
+impl<T> CircularList<T> {
+    pub fn new() -> Self {
+        CircularList::with_capacity(0)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        CircularList {
+            nodes: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            cursor: None,
+            len: 0,
+        }
+    }
+
+    /// Moves the cursor `n` places clockwise.
+    pub fn rotate_right(&mut self, n: usize) {
+        if let Some(mut c) = self.cursor {
+            for _ in 0..n % self.len {
+                c = self.nodes[c].next;
+            }
+            self.cursor = Some(c);
+        }
+    }
+
+    /// Moves the cursor `n` places counter-clockwise.
+    pub fn rotate_left(&mut self, n: usize) {
+        if let Some(mut c) = self.cursor {
+            for _ in 0..n % self.len {
+                c = self.nodes[c].prev;
+            }
+            self.cursor = Some(c);
+        }
+    }
+
+    /// Inserts `value` clockwise of the cursor and moves the cursor onto it.
+    pub fn insert(&mut self, value: T) {
+        let index = self.allocate(value);
+
+        match self.cursor {
+            None => {
+                self.nodes[index].prev = index;
+                self.nodes[index].next = index;
+            },
+            Some(c) => {
+                let next = self.nodes[c].next;
+                self.nodes[index].prev = c;
+                self.nodes[index].next = next;
+                self.nodes[c].next = index;
+                self.nodes[next].prev = index;
+            },
+        }
+
+        self.cursor = Some(index);
+        self.len += 1;
+    }
+
+    /// Removes the value at the cursor, leaving the cursor on the value that was
+    /// clockwise of it.
+    pub fn remove(&mut self) -> Option<T> {
+        let c = self.cursor?;
+        let Node { prev, next, .. } = self.nodes[c];
+
+        self.nodes[prev].next = next;
+        self.nodes[next].prev = prev;
+        self.free.push(c);
+        self.len -= 1;
+        self.cursor = if self.len == 0 { None } else { Some(next) };
+
+        self.nodes[c].value.take()
+    }
+
+    fn allocate(&mut self, value: T) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index].value = Some(value);
+                index
+            },
+            None => {
+                self.nodes.push(Node { value: Some(value), prev: 0, next: 0 });
+                self.nodes.len() - 1
+            },
+        }
+    }
+}
+
+impl<T> Default for CircularList<T> {
+    fn default() -> Self {
+        CircularList::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Removes every value, clockwise from the cursor.
+    fn drain(list: &mut CircularList<i32>) -> Vec<i32> {
+        std::iter::from_fn(|| list.remove()).collect()
+    }
+
+    fn numbered(count: i32) -> CircularList<i32> {
+        let mut list = CircularList::new();
+        for i in 0..count {
+            list.insert(i);
+        }
+        list
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut list = CircularList::new();
+        assert_eq!(None, list.remove());
+
+        list.insert(1);
+        list.insert(2);
+        list.insert(3);
+
+        assert_eq!(vec![3, 1, 2], drain(&mut list));
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut list = numbered(5);
+        list.rotate_right(1);
+        assert_eq!(vec![0, 1, 2, 3, 4], drain(&mut list));
+
+        let mut list = numbered(5);
+        list.rotate_right(1);
+        list.rotate_left(2);
+        assert_eq!(vec![3, 4, 0, 1, 2], drain(&mut list));
+
+        let mut list = numbered(5);
+        list.rotate_right(1);
+        list.rotate_left(2);
+        list.rotate_right(12);
+        assert_eq!(vec![0, 1, 2, 3, 4], drain(&mut list));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut list = numbered(4);
+
+        list.rotate_left(1);
+        assert_eq!(Some(2), list.remove());
+
+        list.insert(7);
+        assert_eq!(vec![7, 0, 1, 3], drain(&mut list));
+    }
+
+    #[test]
+    fn test_remove_until_empty() {
+        let mut list = CircularList::new();
+        list.insert(1);
+        list.insert(2);
+
+        assert_eq!(Some(2), list.remove());
+        assert_eq!(Some(1), list.remove());
+        assert_eq!(None, list.remove());
+
+        list.rotate_right(3);
+        list.insert(5);
+        assert_eq!(vec![5], drain(&mut list));
+    }
+}
@@ -0,0 +1,394 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Display;
+use std::hash::Hash;
+
+use super::union_find::UnionFind;
+
+/// A directed graph keyed by arbitrary node values. Nodes are stored in insertion
+/// order and edges are kept as adjacency lists in both directions.
+#[derive(Clone, Debug)]
+pub struct Graph<K> {
+    keys: Vec<K>,
+    indices: HashMap<K, usize>,
+    successors: Vec<Vec<usize>>,
+    predecessors: Vec<Vec<usize>>,
+}
+
+/// A cycle found in a graph, listed in edge order. The first node is repeated
+/// at the end so `A -> B -> A` is stored as `[A, B, A]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cycle<K>(pub Vec<K>);
+
+impl<K: Display> Display for Cycle<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let parts = self.0.iter().map(|k| k.to_string()).collect::<Vec<String>>();
+        write!(f, "{}", parts.join(" -> "))
+    }
+}
+
+impl<K: Clone + Eq + Hash> Graph<K> {
+    pub fn new() -> Self {
+        Graph {
+            keys: Vec::new(),
+            indices: HashMap::new(),
+            successors: Vec::new(),
+            predecessors: Vec::new(),
+        }
+    }
+
+    /// Adds a node if it isn't already present, returning its index either way.
+    pub fn add_node(&mut self, key: K) -> usize {
+        if let Some(&index) = self.indices.get(&key) {
+            return index;
+        }
+
+        let index = self.keys.len();
+        self.keys.push(key.clone());
+        self.indices.insert(key, index);
+        self.successors.push(Vec::new());
+        self.predecessors.push(Vec::new());
+
+        index
+    }
+
+    /// Adds an edge `from -> to`, creating either node if needed. Duplicate edges are ignored.
+    pub fn add_edge(&mut self, from: K, to: K) {
+        let from = self.add_node(from);
+        let to = self.add_node(to);
+
+        if !self.successors[from].contains(&to) {
+            self.successors[from].push(to);
+            self.predecessors[to].push(from);
+        }
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &K> {
+        self.keys.iter()
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = (&K, &K)> {
+        self.successors.iter()
+            .enumerate()
+            .flat_map(move |(from, tos)| tos.iter().map(move |&to| (&self.keys[from], &self.keys[to])))
+    }
+
+    /// Nodes with an edge from `key`. Unknown keys have no successors.
+    pub fn successors(&self, key: &K) -> Vec<&K> {
+        self.neighbours(key, &self.successors)
+    }
+
+    /// Nodes with an edge to `key`. Unknown keys have no predecessors.
+    pub fn predecessors(&self, key: &K) -> Vec<&K> {
+        self.neighbours(key, &self.predecessors)
+    }
+
+    fn neighbours(&self, key: &K, adjacency: &[Vec<usize>]) -> Vec<&K> {
+        match self.indices.get(key) {
+            Some(&index) => adjacency[index].iter().map(|&i| &self.keys[i]).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Kahn's algorithm. Whenever several nodes are ready at once, the one ordered
+    /// first by `tie_breaker` is taken next. Fails with a cycle if the graph has one.
+    pub fn topological_sort_by<F>(&self, mut tie_breaker: F) -> Result<Vec<K>, Cycle<K>>
+        where F: FnMut(&K, &K) -> Ordering {
+        let mut in_degree = self.predecessors.iter().map(|p| p.len()).collect::<Vec<usize>>();
+        let mut ready = (0..self.len()).filter(|&i| in_degree[i] == 0).collect::<Vec<usize>>();
+        let mut result = Vec::with_capacity(self.len());
+
+        while !ready.is_empty() {
+            let mut best = 0;
+            for i in 1..ready.len() {
+                if tie_breaker(&self.keys[ready[i]], &self.keys[ready[best]]) == Ordering::Less {
+                    best = i;
+                }
+            }
+
+            let next = ready.swap_remove(best);
+            result.push(self.keys[next].clone());
+
+            for &to in self.successors[next].iter() {
+                in_degree[to] -= 1;
+                if in_degree[to] == 0 {
+                    ready.push(to);
+                }
+            }
+        }
+
+        if result.len() == self.len() {
+            Ok(result)
+        } else {
+            Err(self.find_cycle().expect("Topological sort stalled without a cycle"))
+        }
+    }
+
+    /// Returns any cycle in the graph, or `None` if it is acyclic.
+    pub fn find_cycle(&self) -> Option<Cycle<K>> {
+        #[derive(Copy, Clone, PartialEq)]
+        enum Mark {
+            New,
+            InProgress,
+            Done,
+        }
+
+        let mut marks = vec![Mark::New; self.len()];
+
+        for start in 0..self.len() {
+            if marks[start] != Mark::New {
+                continue;
+            }
+
+            // Iterative DFS; each stack entry is a node and the next successor to visit.
+            let mut stack = vec![(start, 0)];
+            marks[start] = Mark::InProgress;
+
+            while let Some(&(node, next)) = stack.last() {
+                if let Some(&to) = self.successors[node].get(next) {
+                    stack.last_mut().unwrap().1 += 1;
+
+                    match marks[to] {
+                        Mark::New => {
+                            marks[to] = Mark::InProgress;
+                            stack.push((to, 0));
+                        },
+                        Mark::InProgress => {
+                            let begin = stack.iter().position(|&(n, _)| n == to).unwrap();
+                            let mut cycle = stack[begin..].iter()
+                                .map(|&(n, _)| self.keys[n].clone())
+                                .collect::<Vec<K>>();
+                            cycle.push(self.keys[to].clone());
+                            return Some(Cycle(cycle));
+                        },
+                        Mark::Done => {},
+                    }
+                } else {
+                    marks[node] = Mark::Done;
+                    stack.pop();
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Every node reachable from `key` by following edges, including `key` itself,
+    /// in breadth-first order.
+    pub fn reachable_from(&self, key: &K) -> Vec<K> {
+        let start = match self.indices.get(key) {
+            Some(&index) => index,
+            None => return Vec::new(),
+        };
+
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        let mut result = Vec::new();
+
+        seen.insert(start);
+        queue.push_back(start);
+
+        while let Some(node) = queue.pop_front() {
+            result.push(self.keys[node].clone());
+
+            for &to in self.successors[node].iter() {
+                if seen.insert(to) {
+                    queue.push_back(to);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Groups of nodes linked by edges in either direction. Each group lists its nodes
+    /// in insertion order, and groups are ordered by their first node.
+    pub fn connected_components(&self) -> Vec<Vec<K>> {
+        let mut sets = UnionFind::new(self.len());
+
+        for (from, tos) in self.successors.iter().enumerate() {
+            for &to in tos.iter() {
+                sets.union(from, to);
+            }
+        }
+
+        sets.groups()
+            .into_iter()
+            .map(|group| group.into_iter().map(|i| self.keys[i].clone()).collect())
+            .collect()
+    }
+
+    /// The graph restricted to `keys`, keeping only edges between them.
+    pub fn subgraph(&self, keys: &[K]) -> Graph<K> {
+        let mut result = Graph::new();
+
+        for key in keys.iter().filter(|k| self.contains(k)) {
+            result.add_node(key.clone());
+        }
+
+        for (from, to) in self.edges() {
+            if result.contains(from) && result.contains(to) {
+                result.add_edge(from.clone(), to.clone());
+            }
+        }
+
+        result
+    }
+}
+
+impl<K: Clone + Eq + Hash + Ord> Graph<K> {
+    /// Topological sort taking the smallest ready node first.
+    pub fn topological_sort(&self) -> Result<Vec<K>, Cycle<K>> {
+        self.topological_sort_by(|a, b| a.cmp(b))
+    }
+}
+
+impl<K: Clone + Eq + Hash + Display> Graph<K> {
+    /// Renders the graph in Graphviz DOT format.
+    pub fn to_dot(&self, name: &str) -> String {
+        self.dot("digraph", "->", name, |_, _| None)
+    }
+
+    /// Renders the graph as an undirected Graphviz graph, for graphs that only hold
+    /// each link in one direction. `label` can give each edge a label.
+    pub fn to_undirected_dot(&self, name: &str, label: impl Fn(&K, &K) -> Option<String>) -> String {
+        self.dot("graph", "--", name, label)
+    }
+
+    fn dot(&self, kind: &str, edge: &str, name: &str, label: impl Fn(&K, &K) -> Option<String>) -> String {
+        let mut result = format!("{} {} {{\n", kind, name);
+
+        for key in self.keys.iter() {
+            result.push_str(&format!("    \"{}\";\n", escape_dot(key)));
+        }
+
+        for (from, to) in self.edges() {
+            let attributes = match label(from, to) {
+                Some(text) => format!(" [label=\"{}\"]", escape_dot(&text)),
+                None => String::new(),
+            };
+            result.push_str(&format!("    \"{}\" {} \"{}\"{};\n", escape_dot(from), edge, escape_dot(to), attributes));
+        }
+
+        result.push_str("}\n");
+        result
+    }
+}
+
+impl<K: Clone + Eq + Hash> Default for Graph<K> {
+    fn default() -> Self {
+        Graph::new()
+    }
+}
+
+fn escape_dot(key: &impl Display) -> String {
+    key.to_string().replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Graph<char> {
+        let mut graph = Graph::new();
+        for (from, to) in [('C', 'A'), ('C', 'F'), ('A', 'B'), ('A', 'D'), ('B', 'E'), ('D', 'E'), ('F', 'E')].iter() {
+            graph.add_edge(*from, *to);
+        }
+        graph
+    }
+
+    #[test]
+    fn test_adjacency() {
+        let graph = example();
+
+        assert_eq!(6, graph.len());
+        assert_eq!(vec![&'A', &'F'], graph.successors(&'C'));
+        assert_eq!(vec![&'B', &'D', &'F'], graph.predecessors(&'E'));
+        assert!(graph.predecessors(&'C').is_empty());
+        assert!(graph.successors(&'Q').is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_edges_ignored() {
+        let mut graph = Graph::new();
+        graph.add_edge(1, 2);
+        graph.add_edge(1, 2);
+
+        assert_eq!(1, graph.edges().count());
+    }
+
+    #[test]
+    fn test_topological_sort() {
+        let graph = example();
+
+        assert_eq!(Ok("CABDFE".chars().collect()), graph.topological_sort());
+        assert_eq!(Ok("CFADBE".chars().collect()), graph.topological_sort_by(|a, b| b.cmp(a)));
+    }
+
+    #[test]
+    fn test_cycle_detection() {
+        let mut graph = example();
+        assert_eq!(None, graph.find_cycle());
+
+        graph.add_edge('E', 'A');
+        let cycle = graph.find_cycle().unwrap();
+        assert_eq!(cycle.0.first(), cycle.0.last());
+        for pair in cycle.0.windows(2) {
+            assert!(graph.successors(&pair[0]).contains(&&pair[1]));
+        }
+
+        assert!(graph.topological_sort().is_err());
+    }
+
+    #[test]
+    fn test_self_loop_cycle() {
+        let mut graph = Graph::new();
+        graph.add_edge("a", "a");
+
+        assert_eq!(Some(Cycle(vec!["a", "a"])), graph.find_cycle());
+        assert_eq!("a -> a", graph.find_cycle().unwrap().to_string());
+    }
+
+    #[test]
+    fn test_reachable_from() {
+        let graph = example();
+
+        assert_eq!(vec!['A', 'B', 'D', 'E'], graph.reachable_from(&'A'));
+        assert_eq!(vec!['E'], graph.reachable_from(&'E'));
+        assert!(graph.reachable_from(&'Q').is_empty());
+    }
+
+    #[test]
+    fn test_connected_components() {
+        let mut graph = example();
+        graph.add_edge('X', 'Y');
+        graph.add_node('Z');
+        graph.add_edge('W', 'Y');
+
+        assert_eq!(vec![vec!['C', 'A', 'F', 'B', 'D', 'E'], vec!['X', 'Y', 'W'], vec!['Z']], graph.connected_components());
+        assert!(Graph::<i32>::new().connected_components().is_empty());
+    }
+
+    #[test]
+    fn test_to_undirected_dot() {
+        let graph = example().subgraph(&['A', 'B', 'D']);
+        let dot = graph.to_undirected_dot("g", |_, to| if *to == 'B' { Some(String::from("x")) } else { None });
+
+        assert_eq!("graph g {\n    \"A\";\n    \"B\";\n    \"D\";\n    \"A\" -- \"B\" [label=\"x\"];\n    \"A\" -- \"D\";\n}\n", dot);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let graph = example().subgraph(&['A', 'B']);
+
+        assert_eq!("digraph steps {\n    \"A\";\n    \"B\";\n    \"A\" -> \"B\";\n}\n", graph.to_dot("steps"));
+    }
+}
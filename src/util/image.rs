@@ -0,0 +1,245 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use super::{AppError, AppResult, Options};
+
+/// The most pixels a scaled image may have, 75MB of colour pixels.
+const MAX_PIXELS: usize = 25_000_000;
+
+/// An image that can be written as a binary Netpbm file. The pixel storage
+/// decides the natural format, but any image can be written in any format.
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Pixels,
+}
+
+enum Pixels {
+    Bits(Vec<bool>),
+    Gray(Vec<u8>),
+    Colour(Vec<Rgb>),
+}
+
+pub type Rgb = [u8; 3];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Black and white, `P4`.
+    Pbm,
+    /// Grayscale, `P5`.
+    Pgm,
+    /// Colour, `P6`.
+    Ppm,
+}
+
+impl Format {
+    /// Picks the format from a file extension, e.g. `out.ppm`.
+    pub fn from_filename(filename: &str) -> AppResult<Format> {
+        let extension = filename.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+
+        match extension.as_ref() {
+            "pbm" => Ok(Format::Pbm),
+            "pgm" => Ok(Format::Pgm),
+            "ppm" => Ok(Format::Ppm),
+            _ => Err(AppError::AppError(format!("Unknown image format for {}, expected .pbm, .pgm or .ppm", filename))),
+        }
+    }
+}
+
+impl Image {
+    /// A black and white image where `true` is a black (set) pixel.
+    pub fn bitmap(width: usize, height: usize, pixel: impl Fn(usize, usize) -> bool) -> Image {
+        Image { width, height, pixels: Pixels::Bits(generate(width, height, pixel)) }
+    }
+
+    pub fn graymap(width: usize, height: usize, pixel: impl Fn(usize, usize) -> u8) -> Image {
+        Image { width, height, pixels: Pixels::Gray(generate(width, height, pixel)) }
+    }
+
+    pub fn pixmap(width: usize, height: usize, pixel: impl Fn(usize, usize) -> Rgb) -> Image {
+        Image { width, height, pixels: Pixels::Colour(generate(width, height, pixel)) }
+    }
+
+    /// Enlarges every pixel into a `factor` x `factor` block, failing rather than
+    /// producing more than `MAX_PIXELS` pixels.
+    pub fn scale(&self, factor: usize) -> AppResult<Image> {
+        let (width, height) = match (self.width.checked_mul(factor), self.height.checked_mul(factor)) {
+            (Some(width), Some(height)) if width.checked_mul(height).is_some_and(|pixels| pixels <= MAX_PIXELS) => (width, height),
+            _ => return Err(AppError::AppError(format!("A {}x{} image scaled by {} is too large to hold in memory", self.width, self.height, factor))),
+        };
+        let source = |x: usize, y: usize| (y / factor) * self.width + x / factor;
+
+        let pixels = match &self.pixels {
+            Pixels::Bits(p) => Pixels::Bits(generate(width, height, |x, y| p[source(x, y)])),
+            Pixels::Gray(p) => Pixels::Gray(generate(width, height, |x, y| p[source(x, y)])),
+            Pixels::Colour(p) => Pixels::Colour(generate(width, height, |x, y| p[source(x, y)])),
+        };
+
+        Ok(Image { width, height, pixels })
+    }
+
+    /// Writes the image to `filename`, choosing the format from its extension.
+    pub fn save(&self, filename: &str) -> AppResult {
+        let format = Format::from_filename(filename)?;
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write(&mut writer, format)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn write(&self, writer: &mut impl Write, format: Format) -> AppResult {
+        match format {
+            Format::Pbm => {
+                write!(writer, "P4\n{} {}\n", self.width, self.height)?;
+
+                // Rows are packed 8 pixels to a byte, most significant bit first.
+                for row in 0..self.height {
+                    let mut packed = vec![0u8; self.width.div_ceil(8)];
+                    for x in 0..self.width {
+                        if self.bit(row * self.width + x) {
+                            packed[x / 8] |= 0x80 >> (x % 8);
+                        }
+                    }
+                    writer.write_all(&packed)?;
+                }
+            },
+            Format::Pgm => {
+                write!(writer, "P5\n{} {}\n255\n", self.width, self.height)?;
+                let bytes = (0..self.width * self.height).map(|i| self.gray(i)).collect::<Vec<u8>>();
+                writer.write_all(&bytes)?;
+            },
+            Format::Ppm => {
+                write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+                let bytes = (0..self.width * self.height).flat_map(|i| self.rgb(i).to_vec()).collect::<Vec<u8>>();
+                writer.write_all(&bytes)?;
+            },
+        };
+
+        Ok(())
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        match &self.pixels {
+            Pixels::Bits(p) => p[i],
+            _ => self.gray(i) < 128,
+        }
+    }
+
+    fn gray(&self, i: usize) -> u8 {
+        match &self.pixels {
+            Pixels::Bits(p) => if p[i] { 0 } else { 255 },
+            Pixels::Gray(p) => p[i],
+            Pixels::Colour(p) => {
+                let [r, g, b] = p[i];
+                ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+            },
+        }
+    }
+
+    fn rgb(&self, i: usize) -> Rgb {
+        match &self.pixels {
+            Pixels::Colour(p) => p[i],
+            _ => {
+                let g = self.gray(i);
+                [g, g, g]
+            },
+        }
+    }
+}
+
+/// Handles the common `--image <file>` option, enlarged by `--scale <n>` if given.
+/// The image is only built when it will be saved, and is told the format it will
+/// be saved in.
+pub fn save_from_options(options: &Options, image: impl FnOnce(Format) -> Image) -> AppResult {
+    if let Some(filename) = options.value("image") {
+        let format = Format::from_filename(filename)?;
+        let scale = options.parse_value::<usize>("scale")?.unwrap_or(1).max(1);
+        image(format).scale(scale)?.save(filename)?;
+        println!("Image written to {}", filename);
+    }
+
+    Ok(())
+}
+
+/// Maps `value` in `min..=max` onto a blue -> green -> yellow -> red heat ramp.
+pub fn heat_colour(value: i64, min: i64, max: i64) -> Rgb {
+    if max <= min {
+        return [0, 0, 255];
+    }
+
+    let t = ((value.clamp(min, max) - min) * 765 / (max - min)) as i32;
+
+    let (r, g, b) = match t {
+        0..=255 => (0, t, 255 - t),
+        256..=510 => (t - 255, 255, 0),
+        _ => (255, 765 - t, 0),
+    };
+
+    [r as u8, g as u8, b as u8]
+}
+
+fn generate<T>(width: usize, height: usize, pixel: impl Fn(usize, usize) -> T) -> Vec<T> {
+    let mut result = Vec::with_capacity(width * height);
+
+    for y in 0..height {
+        for x in 0..width {
+            result.push(pixel(x, y));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written(image: &Image, format: Format) -> Vec<u8> {
+        let mut result = Vec::new();
+        image.write(&mut result, format).unwrap();
+        result
+    }
+
+    #[test]
+    fn test_format_from_filename() {
+        assert_eq!(Format::Pbm, Format::from_filename("out.pbm").unwrap());
+        assert_eq!(Format::Ppm, Format::from_filename("dir.x/OUT.PPM").unwrap());
+        assert!(Format::from_filename("out.png").is_err());
+    }
+
+    #[test]
+    fn test_write_pbm() {
+        let image = Image::bitmap(10, 2, |x, y| x == y || x == 9);
+        let mut expected = b"P4\n10 2\n".to_vec();
+        expected.extend_from_slice(&[0b1000_0000, 0b0100_0000, 0b0100_0000, 0b0100_0000]);
+
+        assert_eq!(expected, written(&image, Format::Pbm));
+    }
+
+    #[test]
+    fn test_write_pgm_and_ppm() {
+        let image = Image::graymap(2, 1, |x, _| if x == 0 { 10 } else { 200 });
+
+        assert_eq!(b"P5\n2 1\n255\n\x0a\xc8".to_vec(), written(&image, Format::Pgm));
+        assert_eq!(b"P6\n2 1\n255\n\x0a\x0a\x0a\xc8\xc8\xc8".to_vec(), written(&image, Format::Ppm));
+        assert_eq!(b"P4\n2 1\n\x80".to_vec(), written(&image, Format::Pbm));
+    }
+
+    #[test]
+    fn test_scale() {
+        let image = Image::bitmap(2, 1, |x, _| x == 1).scale(2).unwrap();
+
+        assert_eq!(b"P4\n4 2\n\x30\x30".to_vec(), written(&image, Format::Pbm));
+        assert!(Image::bitmap(300, 300, |_, _| true).scale(1000).is_err());
+        assert!(Image::bitmap(300, 300, |_, _| true).scale(100_000_000_000).is_err());
+        assert!(Image::bitmap(2, 1, |_, _| true).scale(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_heat_colour() {
+        assert_eq!([0, 0, 255], heat_colour(0, 0, 3));
+        assert_eq!([255, 255, 0], heat_colour(2, 0, 3));
+        assert_eq!([255, 0, 0], heat_colour(9, 0, 3));
+    }
+}
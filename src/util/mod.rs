@@ -1,8 +1,20 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 
+pub mod bk_tree;
+pub mod circular;
+pub mod graph;
+pub mod image;
+pub mod pairs;
+#[cfg(test)]
+pub mod prop;
+pub mod rng;
+pub mod union_find;
+
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum AppError {
     AppError(String),
     IOError(io::Error),
@@ -52,3 +64,97 @@ pub fn run_part_n<F>(day: &str, args: &[String], func: F)
         _ => println!("Please supply a filename as an argument to day {}", day)
     };
 }
+
+pub fn run_part_n_with_options<F>(day: &str, args: &[String], func: F)
+    where F: FnOnce(&String, &Options) -> AppResult {
+    match args {
+        [filename, rest @ ..] => {
+            let result = Options::parse(rest).and_then(|options| func(filename, &options));
+            if let Err(e) = result {
+                println!("Day {} Failed: {}", day, e);
+            }
+        },
+        _ => println!("Please supply a filename as an argument to day {}", day)
+    };
+}
+
+/// For commands that read stdin rather than a file, so every argument is an option.
+pub fn run_with_options<F>(day: &str, args: &[String], func: F)
+    where F: FnOnce(&Options) -> AppResult {
+    if let Err(e) = Options::parse(args).and_then(|options| func(&options)) {
+        println!("Day {} Failed: {}", day, e);
+    }
+}
+
+/// Options given after the filename, as `--name value` or a bare `--flag`.
+pub struct Options {
+    values: HashMap<String, Option<String>>,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> AppResult<Options> {
+        let mut values = HashMap::new();
+        let mut iter = args.iter().peekable();
+
+        while let Some(arg) = iter.next() {
+            if !arg.starts_with("--") {
+                return Err(AppError::AppError(format!("Unexpected argument: {}", arg)));
+            }
+
+            let value = match iter.peek() {
+                Some(next) if !next.starts_with("--") => iter.next().cloned(),
+                _ => None,
+            };
+
+            values.insert(arg[2..].to_string(), value);
+        }
+
+        Ok(Options { values })
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&String> {
+        self.values.get(name).and_then(|v| v.as_ref())
+    }
+
+    pub fn parse_value<T: std::str::FromStr>(&self, name: &str) -> AppResult<Option<T>> {
+        match self.value(name) {
+            Some(value) => match value.parse::<T>() {
+                Ok(result) => Ok(Some(result)),
+                Err(_) => Err(AppError::AppError(format!("Invalid value for --{}: {}", name, value))),
+            },
+            None if self.flag(name) => Err(AppError::AppError(format!("Missing value for --{}", name))),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = Options::parse(&args("--image out.ppm --verbose --size 12")).unwrap();
+
+        assert_eq!(Some(&String::from("out.ppm")), options.value("image"));
+        assert!(options.flag("verbose"));
+        assert_eq!(None, options.value("verbose"));
+        assert_eq!(Some(12), options.parse_value::<usize>("size").unwrap());
+        assert_eq!(None, options.parse_value::<usize>("missing").unwrap());
+        assert!(options.parse_value::<usize>("image").is_err());
+        assert!(options.parse_value::<usize>("verbose").is_err());
+    }
+
+    #[test]
+    fn test_parse_options_rejects_positional() {
+        assert!(Options::parse(&args("out.ppm")).is_err());
+    }
+}
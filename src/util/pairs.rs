@@ -0,0 +1,67 @@
+/// Every unordered pair `(a, b)` of distinct positions in a slice, with `a` before `b`,
+/// borrowing rather than cloning the items.
+pub fn unordered_pairs<T>(items: &[T]) -> UnorderedPairs<'_, T> {
+    UnorderedPairs {
+        items,
+        first: 0,
+        second: 1,
+    }
+}
+
+pub struct UnorderedPairs<'a, T> {
+    items: &'a [T],
+    first: usize,
+    second: usize,
+}
+
+impl<'a, T> Iterator for UnorderedPairs<'a, T> {
+    type Item = (&'a T, &'a T);
+
+    fn next(&mut self) -> Option<(&'a T, &'a T)> {
+        if self.second >= self.items.len() {
+            self.first += 1;
+            self.second = self.first + 1;
+        }
+
+        if self.second >= self.items.len() {
+            return None;
+        }
+
+        let result = (&self.items[self.first], &self.items[self.second]);
+        self.second += 1;
+
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(items: &[i32]) -> Vec<(i32, i32)> {
+        unordered_pairs(items).map(|(a, b)| (*a, *b)).collect()
+    }
+
+    #[test]
+    fn test_short_inputs() {
+        assert!(pairs(&[]).is_empty());
+        assert!(pairs(&[1]).is_empty());
+        assert_eq!(vec![(1, 2)], pairs(&[1, 2]));
+    }
+
+    #[test]
+    fn test_all_pairs_in_order() {
+        assert_eq!(vec![(1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)], pairs(&[1, 2, 3, 4]));
+        assert_eq!(45, unordered_pairs(&[0; 10]).count());
+    }
+
+    #[test]
+    fn test_exhausted_iterator_stays_exhausted() {
+        let items = [1, 2];
+        let mut iter = unordered_pairs(&items);
+
+        assert!(iter.next().is_some());
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+    }
+}
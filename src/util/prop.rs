@@ -0,0 +1,172 @@
+//! Minimal property-based testing. `check` runs a property against many
+//! generated cases; on failure it shrinks the input and panics with the seed
+//! so the case can be replayed with `PROP_SEED=<seed> cargo test <name>`.
+//! `PROP_CASES` overrides the number of cases run.
+
+use std::fmt::Debug;
+
+use super::rng::Rng;
+
+const DEFAULT_CASES: u64 = 100;
+const MAX_SHRINK_STEPS: usize = 1000;
+
+pub fn check<T, G, P>(name: &str, generate: G, property: P)
+    where T: Shrink + Debug, G: Fn(&mut Rng) -> T, P: Fn(&T) -> bool {
+    let seeds = match env_u64("PROP_SEED") {
+        Some(seed) => vec![seed],
+        None => {
+            let cases = env_u64("PROP_CASES").unwrap_or(DEFAULT_CASES);
+            let mut seeder = Rng::new(name.bytes().fold(0, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u64)));
+            (0..cases).map(|_| seeder.next_u64()).collect()
+        }
+    };
+
+    for seed in seeds {
+        let input = generate(&mut Rng::new(seed));
+
+        if !property(&input) {
+            let shrunk = shrink_failure(input, &property);
+            panic!("Property {} failed for seed {} (replay with PROP_SEED={})\nShrunk input: {:?}", name, seed, seed, shrunk);
+        }
+    }
+}
+
+/// Greedily replaces the failing input with the first smaller candidate that still fails.
+fn shrink_failure<T: Shrink, P: Fn(&T) -> bool>(mut input: T, property: &P) -> T {
+    for _ in 0..MAX_SHRINK_STEPS {
+        match input.shrink().into_iter().find(|candidate| !property(candidate)) {
+            Some(smaller) => input = smaller,
+            None => break,
+        }
+    }
+
+    input
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    std::env::var(name).ok().and_then(|v| v.parse().ok())
+}
+
+/// Produces simpler versions of a value, simplest first.
+pub trait Shrink: Sized {
+    fn shrink(&self) -> Vec<Self>;
+}
+
+macro_rules! shrink_integer {
+    ($($t:ty),*) => {
+        $(impl Shrink for $t {
+            fn shrink(&self) -> Vec<$t> {
+                let mut result = Vec::new();
+                let mut delta = *self / 2;
+
+                if *self != 0 {
+                    result.push(0);
+                }
+
+                while delta != 0 {
+                    result.push(*self - delta);
+                    delta /= 2;
+                }
+
+                result
+            }
+        })*
+    };
+}
+
+shrink_integer!(i32, i64, usize);
+
+impl Shrink for char {
+    fn shrink(&self) -> Vec<char> {
+        match *self {
+            'a' => Vec::new(),
+            'A' => vec!['a'],
+            _ => vec!['a', 'A'],
+        }
+    }
+}
+
+impl Shrink for String {
+    fn shrink(&self) -> Vec<String> {
+        self.chars().collect::<Vec<char>>()
+            .shrink()
+            .into_iter()
+            .map(|chars| chars.into_iter().collect())
+            .collect()
+    }
+}
+
+impl<T: Shrink + Clone> Shrink for Vec<T> {
+    fn shrink(&self) -> Vec<Vec<T>> {
+        let mut result = Vec::new();
+
+        // Drop progressively smaller chunks, then simplify single elements.
+        let mut chunk = self.len();
+        while chunk > 0 {
+            for start in (0..=self.len() - chunk).step_by(chunk) {
+                let mut smaller = self[..start].to_vec();
+                smaller.extend_from_slice(&self[start + chunk..]);
+                result.push(smaller);
+            }
+            chunk /= 2;
+        }
+
+        for (i, item) in self.iter().enumerate() {
+            for simpler in item.shrink() {
+                let mut copy = self.clone();
+                copy[i] = simpler;
+                result.push(copy);
+            }
+        }
+
+        result
+    }
+}
+
+impl<A: Shrink + Clone, B: Shrink + Clone> Shrink for (A, B) {
+    fn shrink(&self) -> Vec<(A, B)> {
+        let mut result = self.0.shrink().into_iter().map(|a| (a, self.1.clone())).collect::<Vec<(A, B)>>();
+        result.extend(self.1.shrink().into_iter().map(|b| (self.0.clone(), b)));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer_shrinks_towards_zero() {
+        assert_eq!(vec![0, 5, 8, 9], 10.shrink());
+        assert_eq!(vec![0, -2, -3], (-4i32).shrink());
+        assert!(0usize.shrink().is_empty());
+    }
+
+    #[test]
+    fn test_vec_shrinks_by_removal_first() {
+        let shrunk = vec![3, 4].shrink();
+
+        assert!(shrunk[0].is_empty());
+        assert_eq!(vec![4], shrunk[1]);
+        assert_eq!(vec![3], shrunk[2]);
+    }
+
+    #[test]
+    fn test_failure_is_shrunk() {
+        let property = |v: &Vec<i32>| v.iter().all(|&x| x < 50);
+        let shrunk = shrink_failure(vec![1, 70, 3, 99], &property);
+
+        assert_eq!(vec![50], shrunk);
+    }
+
+    #[test]
+    fn test_passing_property() {
+        check("sum of squares is non-negative", |rng| rng.range(-1000, 1000), |x| x * x >= 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "replay with PROP_SEED=")]
+    fn test_failing_property_reports_seed() {
+        check("always small", |rng| rng.range(0, 100), |&x| x < 90);
+    }
+}
@@ -0,0 +1,88 @@
+/// Disjoint sets over `0..n`, with path halving and union by size.
+pub struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    /// `n` singleton sets.
+    pub fn new(n: usize) -> Self {
+        UnionFind {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    /// The representative of the set holding `x`.
+    pub fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+
+        x
+    }
+
+    /// Merges the sets holding `a` and `b`, returning `false` if they were already one set.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+
+        true
+    }
+
+    /// Every set, each in ascending order, ordered by their smallest member.
+    pub fn groups(&mut self) -> Vec<Vec<usize>> {
+        let mut slots: Vec<Option<usize>> = vec![None; self.parent.len()];
+        let mut result: Vec<Vec<usize>> = Vec::new();
+
+        for x in 0..self.parent.len() {
+            let root = self.find(x);
+            match slots[root] {
+                Some(slot) => result[slot].push(x),
+                None => {
+                    slots[root] = Some(result.len());
+                    result.push(vec![x]);
+                },
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_union() {
+        let mut sets = UnionFind::new(5);
+
+        assert!(sets.union(0, 3));
+        assert!(sets.union(4, 3));
+        assert!(!sets.union(0, 4));
+        assert_eq!(sets.find(0), sets.find(4));
+        assert_ne!(sets.find(0), sets.find(1));
+    }
+
+    #[test]
+    fn test_groups() {
+        let mut sets = UnionFind::new(6);
+        sets.union(5, 1);
+        sets.union(2, 4);
+        sets.union(4, 1);
+
+        assert_eq!(vec![vec![0], vec![1, 2, 4, 5], vec![3]], sets.groups());
+        assert!(UnionFind::new(0).groups().is_empty());
+    }
+}